@@ -0,0 +1,484 @@
+use crate::{
+    identification::known_name,
+    player::try_player_move,
+    prelude::*,
+    tilemap::{RevealedTileMap, TileType},
+};
+
+const MAX_EXPLORE_DEPTH: f32 = 1024.0;
+
+/// Where exploring leads next
+#[derive(Debug, PartialEq)]
+enum ExploreStep {
+    Step(Point),
+    /// There are no revealed tiles left bordering unrevealed ones
+    Explored,
+    /// There are unrevealed tiles left, but the player can't get to any of them, e.g. because a
+    /// monster blocks the way
+    Unreachable,
+}
+
+/// Plan a path for the player to a revealed, walkable tile. Returns the steps to take, not
+/// including the player's current position.
+pub fn plan_travel_path(world: &World, x: i32, y: i32) -> Option<Vec<Point>> {
+    let player_pos = world
+        .query::<(&Player, &Position)>()
+        .into_iter()
+        .next()
+        .map(|(_, (_, pos))| *pos)?;
+
+    let mut map_query = world.query::<&TileMap>();
+    let (_, map) = map_query.into_iter().next()?;
+
+    if !map.is_tile_revealed(x, y) || map.is_tile_blocked(x, y) {
+        return None;
+    }
+
+    let start = map.get_index(player_pos.x, player_pos.y)?;
+    let end = map.get_index(x, y)?;
+
+    if start == end {
+        return None;
+    }
+
+    let nav = a_star_search(start, end, &RevealedTileMap(map));
+
+    if !nav.success {
+        return None;
+    }
+
+    let path = nav
+        .steps
+        .iter()
+        .skip(1)
+        .map(|i| Point::from_tuple(map.get_coords(*i)))
+        .collect::<Vec<_>>();
+
+    Some(path)
+}
+
+/// Start walking the player towards `(x, y)`, taking the first step immediately
+pub fn start_travel(world: &mut World, x: i32, y: i32) -> anyhow::Result<RunState> {
+    match plan_travel_path(world, x, y) {
+        Some(path) => start_auto_move(
+            world,
+            AutoMoveMode::Travel(path.into_iter().rev().collect()),
+        ),
+        None => Ok(RunState::AwaitingInput),
+    }
+}
+
+/// Start exploring towards the nearest unrevealed tiles, taking the first step immediately
+pub fn start_explore(world: &mut World) -> anyhow::Result<RunState> {
+    if !visible_entities::<Monster>(world).is_empty() {
+        GameLog::resource_push(world, "You can't explore with monsters in view.")?;
+        return Ok(RunState::AwaitingInput);
+    }
+
+    start_auto_move(world, AutoMoveMode::Explore)
+}
+
+/// Start running in a direction, taking the first step immediately
+pub fn start_run(world: &mut World, dx: i32, dy: i32) -> anyhow::Result<RunState> {
+    start_auto_move(
+        world,
+        AutoMoveMode::Run {
+            dx,
+            dy,
+            open_neighbors: None,
+        },
+    )
+}
+
+/// Known places the player can travel to, nearest first
+pub fn known_travel_destinations(world: &World) -> Vec<(String, Point)> {
+    let player_pos = match world
+        .query::<(&Player, &Position)>()
+        .into_iter()
+        .next()
+        .map(|(_, (_, pos))| *pos)
+    {
+        Some(pos) => pos,
+        None => return Vec::new(),
+    };
+
+    let mut destinations = Vec::new();
+
+    if let Some((_, map)) = world.query::<&TileMap>().into_iter().next() {
+        for (x, y) in map.find_revealed_tiles(TileType::DownStairs) {
+            destinations.push(("Down stairs".to_string(), Point::new(x, y)));
+        }
+
+        for (_, (_, name, pos)) in world.query::<(&Item, &Name, &Position)>().into_iter() {
+            if map.is_tile_visible(pos.x, pos.y) {
+                destinations.push((known_name(world, name.as_str()), pos.to_point()));
+            }
+        }
+    }
+
+    destinations.sort_by_key(|(_, point)| {
+        let dx = point.x - player_pos.x;
+        let dy = point.y - player_pos.y;
+        dx * dx + dy * dy
+    });
+
+    destinations
+}
+
+fn start_auto_move(world: &mut World, mode: AutoMoveMode) -> anyhow::Result<RunState> {
+    let player = world.resource_entity::<Player>()?;
+    let hp = world.get::<CombatStats>(player)?.hp;
+    let combat_message_count = world
+        .resource::<GameLog>()?
+        .map(|gl| gl.combat_message_count)?;
+
+    world.insert_one(
+        player,
+        AutoMove {
+            mode,
+            known_monsters: visible_entities::<Monster>(world),
+            known_items: visible_items(world),
+            hp,
+            combat_message_count,
+        },
+    )?;
+
+    Ok(continue_auto_move(world)?.unwrap_or(RunState::AwaitingInput))
+}
+
+/// Take the next step of the player's `AutoMove`, if any. Returns `None` if the player is not
+/// moving automatically or the movement was interrupted.
+pub fn continue_auto_move(world: &mut World) -> anyhow::Result<Option<RunState>> {
+    let player = world.resource_entity::<Player>()?;
+
+    let mut auto_move = match world.remove_one::<AutoMove>(player) {
+        Ok(auto_move) => auto_move,
+        Err(_) => return Ok(None),
+    };
+
+    if let Some(reason) = interruption(world, player, &auto_move)? {
+        GameLog::resource_push(world, reason)?;
+        return Ok(None);
+    }
+
+    let player_pos = *world.get::<Position>(player)?;
+
+    let next = match &mut auto_move.mode {
+        AutoMoveMode::Travel(path) => path.pop(),
+        AutoMoveMode::Explore => match next_explore_step(world, player_pos) {
+            ExploreStep::Step(next) => Some(next),
+            ExploreStep::Explored => {
+                GameLog::resource_push(world, "There is nothing left to explore.")?;
+                None
+            }
+            ExploreStep::Unreachable => {
+                GameLog::resource_push(world, "You can't reach the rest of the level.")?;
+                None
+            }
+        },
+        AutoMoveMode::Run {
+            dx,
+            dy,
+            open_neighbors,
+        } => next_run_step(world, player_pos, *dx, *dy, open_neighbors),
+    };
+
+    let next = match next {
+        Some(next) => next,
+        None => return Ok(None),
+    };
+
+    match try_player_move(world, next.x - player_pos.x, next.y - player_pos.y)? {
+        RunState::PlayerTurn => {
+            let is_finished = match &auto_move.mode {
+                AutoMoveMode::Travel(path) => path.is_empty(),
+                AutoMoveMode::Explore | AutoMoveMode::Run { .. } => false,
+            };
+
+            if !is_finished {
+                world.insert_one(player, auto_move)?;
+            }
+
+            Ok(Some(RunState::PlayerTurn))
+        }
+        _ => {
+            if !matches!(auto_move.mode, AutoMoveMode::Run { .. }) {
+                GameLog::resource_push(world, "Your path is blocked.")?;
+            }
+
+            Ok(None)
+        }
+    }
+}
+
+/// Stop any automatic movement, e.g. because the player pressed a key
+pub fn cancel_auto_move(world: &mut World) {
+    if let Ok(player) = world.resource_entity::<Player>() {
+        let _ = world.remove_one::<AutoMove>(player);
+    }
+}
+
+fn interruption(
+    world: &World,
+    player: Entity,
+    auto_move: &AutoMove,
+) -> anyhow::Result<Option<&'static str>> {
+    let combat_message_count = world
+        .resource::<GameLog>()?
+        .map(|gl| gl.combat_message_count)?;
+
+    if combat_message_count != auto_move.combat_message_count {
+        return Ok(Some("You stop, distracted by the fighting."));
+    }
+
+    if world.get::<CombatStats>(player)?.hp < auto_move.hp {
+        return Ok(Some("You stop, feeling hurt."));
+    }
+
+    if visible_entities::<Monster>(world)
+        .iter()
+        .any(|monster| !auto_move.known_monsters.contains(monster))
+    {
+        return Ok(Some("You stop as a monster comes into view."));
+    }
+
+    if visible_items(world)
+        .iter()
+        .any(|item| !auto_move.known_items.contains(item))
+    {
+        return Ok(Some("You stop, noticing an item."));
+    }
+
+    Ok(None)
+}
+
+/// Find the next step towards the nearest revealed tile bordering unrevealed ones, only walking
+/// over tiles the player already knows about
+fn next_explore_step(world: &World, player_pos: Position) -> ExploreStep {
+    let mut map_query = world.query::<&TileMap>();
+    let map = match map_query.into_iter().next() {
+        Some((_, map)) => map,
+        None => return ExploreStep::Unreachable,
+    };
+
+    let frontier = map.get_unexplored_frontier();
+
+    if frontier.is_empty() {
+        return ExploreStep::Explored;
+    }
+
+    let revealed_map = RevealedTileMap(map);
+    let player_index = match map.get_index(player_pos.x, player_pos.y) {
+        Some(index) => index,
+        None => return ExploreStep::Unreachable,
+    };
+
+    let dijkstra = DijkstraMap::new(
+        map.get_width(),
+        map.get_height(),
+        &frontier,
+        &revealed_map,
+        MAX_EXPLORE_DEPTH,
+    );
+
+    match DijkstraMap::find_lowest_exit(&dijkstra, player_index, &revealed_map) {
+        Some(next) if dijkstra.map[next] < dijkstra.map[player_index] => {
+            ExploreStep::Step(Point::from_tuple(map.get_coords(next)))
+        }
+        _ => ExploreStep::Unreachable,
+    }
+}
+
+/// Find the next step of a run, or `None` if the player has reached a branch, an opening, an item
+/// which doesn't rot away, or the stairs
+fn next_run_step(
+    world: &World,
+    player_pos: Position,
+    dx: i32,
+    dy: i32,
+    open_neighbors: &mut Option<usize>,
+) -> Option<Point> {
+    let mut map_query = world.query::<&TileMap>();
+    let (_, map) = map_query.into_iter().next()?;
+
+    let neighbors = map.count_walkable_neighbors(player_pos.x, player_pos.y);
+
+    if let Some(previous_neighbors) = *open_neighbors {
+        if neighbors != previous_neighbors {
+            return None;
+        }
+
+        if map.is_tile_down_stairs(player_pos.x, player_pos.y) {
+            return None;
+        }
+
+        if world
+            .query::<(&Item, &Position)>()
+            .without::<Decays>()
+            .into_iter()
+            .any(|(_, (_, pos))| *pos == player_pos)
+        {
+            return None;
+        }
+    }
+
+    *open_neighbors = Some(neighbors);
+
+    Some(Point::new(player_pos.x + dx, player_pos.y + dy))
+}
+
+/// Items on tiles the player can currently see which are worth stopping for, i.e. not corpses
+/// or anything else which rots away
+fn visible_items(world: &World) -> Vec<Entity> {
+    visible_entities::<Item>(world)
+        .into_iter()
+        .filter(|item| world.get::<Decays>(*item).is_err())
+        .collect()
+}
+
+/// Entities with a `T` component on tiles the player can currently see
+fn visible_entities<T: Component>(world: &World) -> Vec<Entity> {
+    let mut map_query = world.query::<&TileMap>();
+    let map = match map_query.into_iter().next() {
+        Some((_, map)) => map,
+        None => return Vec::new(),
+    };
+
+    let entities = world
+        .query::<(&T, &Position)>()
+        .into_iter()
+        .filter(|(_, (_, pos))| map.is_tile_visible(pos.x, pos.y))
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    entities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A player in the left room of a map whose right room is walled off
+    fn walled_off_world() -> World {
+        let mut map = TileMap::from_ascii(&["#########", "#...#...#", "#...#...#", "#########"]);
+
+        for y in 0..map.get_height() {
+            for x in 0..5 {
+                map.set_tile_revealed(x, y);
+            }
+        }
+
+        let mut world = World::new();
+        world.spawn_resource(map, ()).unwrap();
+        world.spawn_resource(GameLog::new(), ()).unwrap();
+        world
+            .spawn_resource(
+                Player,
+                (
+                    Position { x: 1, y: 1 },
+                    CombatStats {
+                        max_hp: 10,
+                        hp: 10,
+                        defense: 0,
+                        power: 1,
+                    },
+                ),
+            )
+            .unwrap();
+
+        world
+    }
+
+    #[test]
+    fn test_explore_unreachable() {
+        let mut world = walled_off_world();
+
+        // A glimpse of the walled off room leaves it to explore, but there is no way in
+        world
+            .resource::<TileMap>()
+            .unwrap()
+            .map(|mut map| map.set_tile_revealed(5, 1))
+            .unwrap();
+
+        assert_eq!(
+            next_explore_step(&world, Position { x: 1, y: 1 }),
+            ExploreStep::Unreachable
+        );
+
+        assert_eq!(start_explore(&mut world).unwrap(), RunState::AwaitingInput);
+        assert_eq!(
+            world
+                .resource::<GameLog>()
+                .unwrap()
+                .map(|gl| gl.entries.clone())
+                .unwrap(),
+            vec!["You can't reach the rest of the level.".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_explore_finished() {
+        let world = walled_off_world();
+
+        world
+            .resource::<TileMap>()
+            .unwrap()
+            .map(|mut map| map.reveal_all_tiles())
+            .unwrap();
+
+        assert_eq!(
+            next_explore_step(&world, Position { x: 1, y: 1 }),
+            ExploreStep::Explored
+        );
+    }
+
+    #[test]
+    fn test_corpses_are_not_worth_stopping_for() {
+        let mut world = walled_off_world();
+        world
+            .resource::<TileMap>()
+            .unwrap()
+            .map(|mut map| {
+                for x in 1..4 {
+                    map.set_tile_visible(x, 1);
+                }
+            })
+            .unwrap();
+
+        let player = world.resource_entity::<Player>().unwrap();
+        let auto_move = AutoMove {
+            mode: AutoMoveMode::Explore,
+            known_monsters: Vec::new(),
+            known_items: Vec::new(),
+            hp: 10,
+            combat_message_count: 0,
+        };
+
+        world.spawn((
+            Item,
+            Corpse,
+            Decays { turns_left: 5 },
+            Position { x: 2, y: 1 },
+        ));
+        assert_eq!(interruption(&world, player, &auto_move).unwrap(), None);
+
+        // Running over the corpse doesn't stop on it either
+        let mut open_neighbors = Some(
+            world
+                .resource::<TileMap>()
+                .unwrap()
+                .map(|map| map.count_walkable_neighbors(2, 1))
+                .unwrap(),
+        );
+        assert_eq!(
+            next_run_step(&world, Position { x: 2, y: 1 }, 1, 0, &mut open_neighbors),
+            Some(Point::new(3, 1))
+        );
+
+        world.spawn((Item, Position { x: 3, y: 1 }));
+        assert_eq!(
+            interruption(&world, player, &auto_move).unwrap(),
+            Some("You stop, noticing an item.")
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::prelude::*;
+
+const BITS_PER_WORD: usize = 64;
+
+/// A grid of bools packed into words, for cheap lookups and clears of per-tile flags
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BitGrid {
+    width: i32,
+    height: i32,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn new(width: i32, height: i32) -> Self {
+        let num_bits = width.max(0) as usize * height.max(0) as usize;
+
+        Self {
+            width,
+            height,
+            words: vec![0; num_bits.div_ceil(BITS_PER_WORD)],
+        }
+    }
+
+    /// Clear every bit, resizing the grid if needed
+    pub fn reset(&mut self, width: i32, height: i32) {
+        if self.width == width && self.height == height {
+            for word in self.words.iter_mut() {
+                *word = 0;
+            }
+        } else {
+            *self = Self::new(width, height);
+        }
+    }
+
+    fn get_index(&self, x: i32, y: i32) -> Option<usize> {
+        if x >= 0 && x < self.width && y >= 0 && y < self.height {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Out of bounds points are never set
+    pub fn get(&self, x: i32, y: i32) -> bool {
+        match self.get_index(x, y) {
+            Some(index) => self.words[index / BITS_PER_WORD] & (1 << (index % BITS_PER_WORD)) != 0,
+            None => false,
+        }
+    }
+
+    /// Setting out of bounds points is ignored
+    pub fn set(&mut self, x: i32, y: i32, value: bool) {
+        if let Some(index) = self.get_index(x, y) {
+            let mask = 1 << (index % BITS_PER_WORD);
+
+            if value {
+                self.words[index / BITS_PER_WORD] |= mask;
+            } else {
+                self.words[index / BITS_PER_WORD] &= !mask;
+            }
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.get(point.x, point.y)
+    }
+
+    /// Every set point, in row order
+    pub fn iter(&self) -> impl Iterator<Item = Point> + '_ {
+        let width = self.width.max(1) as usize;
+
+        self.words
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| **word != 0)
+            .flat_map(move |(word_index, word)| {
+                (0..BITS_PER_WORD)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| {
+                        let index = word_index * BITS_PER_WORD + bit;
+                        Point::new((index % width) as i32, (index / width) as i32)
+                    })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_get_and_iter() {
+        let mut grid = BitGrid::new(70, 3);
+
+        grid.set(0, 0, true);
+        grid.set(69, 0, true);
+        grid.set(5, 2, true);
+        grid.set(70, 0, true);
+        grid.set(-1, 1, true);
+
+        assert!(grid.get(0, 0));
+        assert!(grid.get(69, 0));
+        assert!(grid.get(5, 2));
+        assert!(!grid.get(1, 0));
+        assert!(!grid.get(70, 0));
+        assert_eq!(grid.iter().count(), 3);
+        assert_eq!(
+            grid.iter().collect::<Vec<_>>(),
+            vec![Point::new(0, 0), Point::new(69, 0), Point::new(5, 2)]
+        );
+
+        grid.set(69, 0, false);
+        assert!(!grid.get(69, 0));
+
+        grid.reset(70, 3);
+        assert_eq!(grid.iter().count(), 0);
+    }
+}
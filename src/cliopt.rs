@@ -1,10 +1,19 @@
 use anyhow::{anyhow, Context};
-use std::{convert::TryInto, fs, path::PathBuf};
+use std::{
+    convert::TryInto,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
 use structopt::StructOpt;
 
-use crate::config::{self, Config};
+use crate::{
+    config::{self, Config, ConfigErrors},
+    scores::{self, ScoreSort},
+};
 
 const DEFAULT_CONFIG_PATH: &'static str = ".rl-config";
+const DEFAULT_REPLAY_PATH: &str = ".rl-replay";
 
 #[derive(Debug, StructOpt)]
 #[structopt(
@@ -25,18 +34,102 @@ struct Opt {
     /// Create a default config file
     #[structopt(short = "z", long, help = "create a default config file")]
     create_config: bool,
+
+    /// Check a config file and report every problem found
+    #[structopt(long, help = "check the config file for errors and exit")]
+    check_config: bool,
+
+    /// Print the score table
+    #[structopt(long, help = "print the high score table and exit")]
+    scores: bool,
+
+    /// Order of the printed score table
+    #[structopt(
+        long,
+        default_value = "depth",
+        requires = "scores",
+        help = "sort the high score table by depth, xp, turns or recent"
+    )]
+    sort_scores: ScoreSort,
+
+    /// Seed for the world RNG
+    #[structopt(long, help = "seed the game, instead of picking a random seed")]
+    seed: Option<u64>,
+
+    /// Where to record the replay of a new game
+    #[structopt(
+        long,
+        parse(from_os_str),
+        default_value = DEFAULT_REPLAY_PATH,
+        help = "where to record the replay of this game"
+    )]
+    record: PathBuf,
+
+    /// Replay a recorded game
+    #[structopt(long, parse(from_os_str), help = "replay a recorded game")]
+    replay: Option<PathBuf>,
+
+    /// Replay speed in the window
+    #[structopt(
+        long,
+        default_value = "10",
+        help = "replay speed in actions per second (change with + and - while replaying)"
+    )]
+    replay_speed: f32,
+
+    /// Replay without opening a window
+    #[structopt(
+        long,
+        requires = "replay",
+        help = "run the replay without a window and print the final state hash"
+    )]
+    headless: bool,
+
+    /// Expected final state hash of a headless replay
+    #[structopt(
+        long,
+        requires = "headless",
+        parse(try_from_str = parse_hash),
+        help = "exit with an error if the final state hash of a headless replay differs"
+    )]
+    expect_hash: Option<u64>,
+}
+
+fn parse_hash(s: &str) -> Result<u64, std::num::ParseIntError> {
+    u64::from_str_radix(s, 16)
 }
 
 pub struct ParsedOpt {
     pub config: Config,
+    pub seed: Option<u64>,
+    pub record: PathBuf,
+    pub replay: Option<PathBuf>,
+    pub replay_speed: f32,
+    pub headless: bool,
+    pub expect_hash: Option<u64>,
 }
 
 pub fn parse_opt_args() -> anyhow::Result<ParsedOpt> {
     let opt = Opt::from_args();
 
+    if opt.scores {
+        scores::print_scores(opt.sort_scores)?;
+        process::exit(0);
+    }
+
+    if opt.check_config {
+        let path = opt
+            .config
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+
+        check_config(&path)?;
+    }
+
     let user_config = if opt.create_config {
         let path = opt
             .config
+            .clone()
             .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
 
         if path.exists() {
@@ -52,13 +145,14 @@ pub fn parse_opt_args() -> anyhow::Result<ParsedOpt> {
 
         user_config
     } else {
-        match opt.config {
+        match &opt.config {
             Some(path) => {
                 let config_string_string = fs::read_to_string(path)?;
-                toml::from_str(&config_string_string).context("Error parsing config file")?
+                config::user_config_from_str(&config_string_string)
+                    .context("Error parsing config file")?
             }
             None => match fs::read_to_string(DEFAULT_CONFIG_PATH) {
-                Ok(s) => toml::from_str(&s)?,
+                Ok(s) => config::user_config_from_str(&s)?,
                 Err(_) => config::default_user_config(),
             },
         }
@@ -66,5 +160,37 @@ pub fn parse_opt_args() -> anyhow::Result<ParsedOpt> {
 
     let config = user_config.try_into()?;
 
-    Ok(ParsedOpt { config })
+    Ok(ParsedOpt {
+        config,
+        seed: opt.seed,
+        record: opt.record,
+        replay: opt.replay,
+        replay_speed: opt.replay_speed.max(0.5),
+        headless: opt.headless,
+        expect_hash: opt.expect_hash,
+    })
+}
+
+/// Report every problem in the config file at `path`, then exit. Exits non-zero if any were found.
+fn check_config(path: &Path) -> anyhow::Result<()> {
+    let config_string = fs::read_to_string(path)
+        .with_context(|| format!("Could not read config '{}'", path.to_string_lossy()))?;
+
+    match config::config_from_str(&config_string) {
+        Ok(_) => {
+            println!("Config '{}' is OK.", path.to_string_lossy());
+            process::exit(0);
+        }
+        Err(ConfigErrors(errors)) => {
+            eprintln!(
+                "Found {} problem(s) in config '{}':",
+                errors.len(),
+                path.to_string_lossy()
+            );
+            for err in errors {
+                eprintln!("  {}", err);
+            }
+            process::exit(1);
+        }
+    }
 }
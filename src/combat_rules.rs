@@ -0,0 +1,244 @@
+use bracket_lib::random::RandomNumberGenerator;
+
+use crate::components::DamageType;
+
+/// Armor class of something with no defense. Each point of defense adds one to it.
+const BASE_ARMOR_CLASS: i32 = 10;
+
+/// Sides of the die rolled to hit. Rolling the highest is a critical hit and rolling a 1 is a
+/// fumble, whatever the bonuses.
+const ATTACK_DIE: i32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DamageDice {
+    pub n_dice: i32,
+    pub die_type: i32,
+    pub bonus: i32,
+}
+
+impl DamageDice {
+    /// A single die which deals `power` damage on average, e.g. 1d9 for a power of 5
+    pub fn with_average(power: i32) -> Self {
+        Self {
+            n_dice: 1,
+            die_type: i32::max(1, power * 2 - 1),
+            bonus: 0,
+        }
+    }
+
+    pub fn roll(self, rng: &mut RandomNumberGenerator) -> i32 {
+        i32::max(0, rng.roll_dice(self.n_dice, self.die_type) + self.bonus)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttackResult {
+    /// Rolled a 1, so missed whatever the odds
+    Fumble,
+    Miss,
+    Hit {
+        damage: i32,
+    },
+    /// Rolled the highest number, so hit with the damage dice rolled twice
+    Critical {
+        damage: i32,
+    },
+}
+
+pub fn armor_class(defense: i32) -> i32 {
+    BASE_ARMOR_CLASS + defense
+}
+
+/// Roll an attack with `to_hit` added to the roll against `armor_class`, which hits if the total
+/// is at least the armor class
+pub fn roll_attack(
+    rng: &mut RandomNumberGenerator,
+    to_hit: i32,
+    armor_class: i32,
+    damage: DamageDice,
+) -> AttackResult {
+    let roll = rng.roll_dice(1, ATTACK_DIE);
+
+    if roll == 1 {
+        AttackResult::Fumble
+    } else if roll == ATTACK_DIE {
+        AttackResult::Critical {
+            damage: damage.roll(rng) + damage.roll(rng),
+        }
+    } else if roll + to_hit >= armor_class {
+        AttackResult::Hit {
+            damage: damage.roll(rng),
+        }
+    } else {
+        AttackResult::Miss
+    }
+}
+
+/// Roll an attack by something with `power` against something with `defense`. Power adds to the
+/// roll to hit and sets the average damage, and defense raises the armor class.
+pub fn roll_power_attack(
+    rng: &mut RandomNumberGenerator,
+    power: i32,
+    defense: i32,
+) -> AttackResult {
+    roll_attack(
+        rng,
+        power,
+        armor_class(defense),
+        DamageDice::with_average(power),
+    )
+}
+
+impl DamageType {
+    pub fn name(self) -> &'static str {
+        match self {
+            DamageType::Physical => "physical damage",
+            DamageType::Fire => "fire",
+            DamageType::Cold => "cold",
+            DamageType::Poison => "poison",
+        }
+    }
+}
+
+/// How a creature's resistances change the damage it takes of some type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageModifier {
+    Normal,
+    /// Takes half damage, rounded down
+    Resisted,
+    /// Takes double damage
+    Vulnerable,
+    /// Takes no damage
+    Immune,
+}
+
+impl DamageModifier {
+    pub fn apply(self, amount: i32) -> i32 {
+        match self {
+            DamageModifier::Normal => amount,
+            DamageModifier::Resisted => amount / 2,
+            DamageModifier::Vulnerable => amount * 2,
+            DamageModifier::Immune => 0,
+        }
+    }
+}
+
+/// Immunity beats everything else, and being both resistant and vulnerable cancels out
+pub fn damage_modifier(resistant: bool, vulnerable: bool, immune: bool) -> DamageModifier {
+    match (resistant, vulnerable, immune) {
+        (_, _, true) => DamageModifier::Immune,
+        (true, false, false) => DamageModifier::Resisted,
+        (false, true, false) => DamageModifier::Vulnerable,
+        _ => DamageModifier::Normal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROLLS: usize = 20_000;
+
+    /// How often each kind of result comes up, and the average damage of hits and of criticals
+    struct Stats {
+        fumbles: f32,
+        misses: f32,
+        hits: f32,
+        criticals: f32,
+        hit_damage: f32,
+        critical_damage: f32,
+    }
+
+    fn roll_many(to_hit: i32, armor_class: i32, damage: DamageDice) -> Stats {
+        let mut rng = RandomNumberGenerator::seeded(49);
+
+        let (mut fumbles, mut misses, mut hits, mut criticals) = (0, 0, 0, 0);
+        let (mut hit_damage, mut critical_damage) = (0, 0);
+
+        for _ in 0..ROLLS {
+            match roll_attack(&mut rng, to_hit, armor_class, damage) {
+                AttackResult::Fumble => fumbles += 1,
+                AttackResult::Miss => misses += 1,
+                AttackResult::Hit { damage } => {
+                    hits += 1;
+                    hit_damage += damage;
+                }
+                AttackResult::Critical { damage } => {
+                    criticals += 1;
+                    critical_damage += damage;
+                }
+            }
+        }
+
+        Stats {
+            fumbles: fumbles as f32 / ROLLS as f32,
+            misses: misses as f32 / ROLLS as f32,
+            hits: hits as f32 / ROLLS as f32,
+            criticals: criticals as f32 / ROLLS as f32,
+            hit_damage: hit_damage as f32 / hits.max(1) as f32,
+            critical_damage: critical_damage as f32 / criticals.max(1) as f32,
+        }
+    }
+
+    fn assert_near(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {} within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_hit_chance() {
+        // Needs a 6 or more: 14 hits and a critical in every 20 rolls
+        let stats = roll_many(5, armor_class(1), DamageDice::with_average(5));
+
+        assert_near(stats.hits, 14.0 / 20.0, 0.015);
+        assert_near(stats.criticals, 1.0 / 20.0, 0.01);
+        assert_near(stats.misses, 4.0 / 20.0, 0.015);
+        assert_near(stats.fumbles, 1.0 / 20.0, 0.01);
+    }
+
+    #[test]
+    fn test_natural_rolls_ignore_the_odds() {
+        // Can't miss except by fumbling
+        let stats = roll_many(100, armor_class(0), DamageDice::with_average(1));
+        assert_eq!(stats.misses, 0.0);
+        assert_near(stats.fumbles, 1.0 / 20.0, 0.01);
+
+        // Can't hit except by a critical
+        let stats = roll_many(0, armor_class(100), DamageDice::with_average(1));
+        assert_eq!(stats.hits, 0.0);
+        assert_near(stats.criticals, 1.0 / 20.0, 0.01);
+    }
+
+    #[test]
+    fn test_damage() {
+        assert_eq!(
+            DamageDice::with_average(5),
+            DamageDice {
+                n_dice: 1,
+                die_type: 9,
+                bonus: 0,
+            }
+        );
+        assert_eq!(DamageDice::with_average(0).die_type, 1);
+
+        // Criticals roll the damage dice twice
+        let stats = roll_many(5, armor_class(1), DamageDice::with_average(5));
+        assert_near(stats.hit_damage, 5.0, 0.15);
+        assert_near(stats.critical_damage, 10.0, 0.6);
+    }
+
+    #[test]
+    fn test_damage_modifier() {
+        assert_eq!(damage_modifier(false, false, false).apply(5), 5);
+        assert_eq!(damage_modifier(true, false, false).apply(5), 2);
+        assert_eq!(damage_modifier(false, true, false).apply(5), 10);
+        assert_eq!(damage_modifier(false, false, true).apply(5), 0);
+        assert_eq!(damage_modifier(true, true, false), DamageModifier::Normal);
+        assert_eq!(damage_modifier(true, true, true), DamageModifier::Immune);
+    }
+}
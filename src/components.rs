@@ -26,12 +26,14 @@ pub struct Renderable {
     pub glyph: FontCharType,
     pub fg: RGB,
     pub bg: RGB,
+    pub fog_fg: RGB,
+    pub fog_bg: RGB,
     pub render_order: i32,
 }
 
 #[derive(Debug)]
 pub struct Viewshed {
-    pub visible_tiles: Vec<Point>,
+    pub visible_tiles: BitGrid,
     pub range: i32,
     pub dirty: bool,
 }
@@ -39,16 +41,77 @@ pub struct Viewshed {
 impl Viewshed {
     pub fn with_range(range: i32) -> Self {
         Self {
-            visible_tiles: Vec::new(),
+            visible_tiles: BitGrid::default(),
             range,
             dirty: true,
         }
     }
 }
 
+/// Can see in the dark, without tiles being lit
+#[derive(Debug)]
+pub struct Darkvision;
+
+/// Lights the tiles it can see within `radius`, fading towards the edge
+#[derive(Debug, Clone, Copy)]
+pub struct LightSource {
+    pub radius: i32,
+    pub color: RGB,
+}
+
 #[derive(Debug)]
 pub struct Monster;
 
+/// What a monster may drop when it dies, besides what it carries. See `spawner::rng_loot`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LootTable {
+    Orc,
+    Goblin,
+}
+
+/// Attacks from up to `range` tiles away with projectiles which deal `power` damage, and backs
+/// away from targets closer than `min_distance` if it can
+#[derive(Debug, Clone, Copy)]
+pub struct RangedAttack {
+    pub range: f32,
+    pub min_distance: f32,
+    pub power: i32,
+    pub projectile: Projectile,
+    pub damage_type: DamageType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projectile {
+    Arrow,
+    MagicBolt,
+}
+
+/// Who an entity sides with. See `faction::reaction` for how factions treat each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Faction {
+    Player,
+    Townsfolk,
+    Orcs,
+    Goblins,
+}
+
+/// NPC which talks to the player using the dialogue with this id in the `DialogueLibrary`
+#[derive(Debug)]
+pub struct Speaker {
+    pub dialogue: String,
+}
+
+/// Non-hostile NPC which buys and sells items. Its stock is the items in its inventory.
+#[derive(Debug)]
+pub struct Vendor;
+
+/// Where an entity is recorded in the `TileMap` index, so it can be updated when it moves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexedPosition {
+    pub position: Position,
+    pub blocks_tile: bool,
+}
+
 #[derive(Debug)]
 pub struct Name(pub String);
 
@@ -67,6 +130,42 @@ impl Display for Name {
 #[derive(Debug)]
 pub struct BlocksTile;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Cold,
+    Poison,
+}
+
+/// Takes half damage of these types
+#[derive(Debug)]
+pub struct Resistant(pub Vec<DamageType>);
+
+/// Takes double damage of these types
+#[derive(Debug)]
+pub struct Vulnerable(pub Vec<DamageType>);
+
+/// Takes no damage of these types
+#[derive(Debug)]
+pub struct Immune(pub Vec<DamageType>);
+
+/// Poisons whatever it hits for `turns` turns, dealing `damage` poison damage each turn
+#[derive(Debug, Clone, Copy)]
+pub struct PoisonsOnHit {
+    pub turns: i32,
+    pub damage: i32,
+}
+
+/// Takes `damage` poison damage every turn for `turns_left` turns
+#[derive(Debug)]
+pub struct Poisoned {
+    pub turns_left: i32,
+    pub damage: i32,
+    /// Who inflicted the poison, for kill counts and cause of death
+    pub source: Option<Entity>,
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct CombatStats {
     pub max_hp: i32,
@@ -78,21 +177,139 @@ pub struct CombatStats {
 #[derive(Debug)]
 pub struct Item;
 
+/// Item which is used up when used
+#[derive(Debug)]
+pub struct Consumable;
+
 #[derive(Debug)]
 pub struct HealingItem {
     pub heal_amount: i32,
 }
 
+/// Grants `Telepathic` to the user for `duration` turns
+#[derive(Debug)]
+pub struct TelepathyItem {
+    pub duration: i32,
+}
+
+/// Reveals the whole map when used
+#[derive(Debug)]
+pub struct MagicMappingItem;
+
+/// Item which shatters when thrown, applying its effect to everything around where it lands
+#[derive(Debug)]
+pub struct Fragile;
+
+/// Identifies every kind of item its user carries when used
+#[derive(Debug)]
+pub struct IdentifyItem;
+
+/// Lifts the curses from every item its user carries when used
+#[derive(Debug)]
+pub struct RemoveCurseItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EquipmentSlot {
+    Melee,
+    Shield,
+}
+
+/// Item which can be equipped in `slot` by using it
+#[derive(Debug)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+/// Worn or wielded by `owner`. Equipped items stay in the owner's inventory.
+#[derive(Debug)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Debug)]
+pub struct MeleePowerBonus {
+    pub power: i32,
+}
+
+#[derive(Debug)]
+pub struct DefenseBonus {
+    pub defense: i32,
+}
+
+/// Added to each bonus of an item, e.g. +1 or -2. The player only sees it once `is_known`.
+#[derive(Debug)]
+pub struct Enchantment {
+    pub level: i32,
+    pub is_known: bool,
+}
+
+/// Item which can't be unequipped, dropped or sold while it is equipped
+#[derive(Debug)]
+pub struct Cursed;
+
+/// Remains of a dead creature, which can be eaten once there is hunger
+#[derive(Debug)]
+pub struct Corpse;
+
+/// Mark on the floor which is only for show, e.g. blood
+#[derive(Debug)]
+pub struct Decal;
+
+/// Rots or fades away after `turns_left` turns, and is despawned
+#[derive(Debug)]
+pub struct Decays {
+    pub turns_left: i32,
+}
+
+/// Gold carried by an entity, or the amount in a gold pile
+#[derive(Debug, Clone, Copy)]
+pub struct Gold {
+    pub amount: i32,
+}
+
+/// What a vendor sells an item for
+#[derive(Debug, Clone, Copy)]
+pub struct Value {
+    pub gold: i32,
+}
+
+/// Item which lets its carrier sense monsters within `range` tiles
+#[derive(Debug)]
+pub struct DetectsMonsters {
+    pub range: i32,
+}
+
+/// Senses every monster on the level
+#[derive(Debug)]
+pub struct Telepathic {
+    pub turns_left: i32,
+}
+
+/// Monster the player knows the position of, even if it is out of sight
+#[derive(Debug)]
+pub struct Detected;
+
 #[derive(Debug)]
 pub struct InitiateAttackCommand {
     pub attacker: Entity,
     pub defender: Entity,
 }
 
+/// Shoot at `defender`, hitting whatever is first in the line of fire
+#[derive(Debug)]
+pub struct InitiateRangedAttackCommand {
+    pub attacker: Entity,
+    pub defender: Entity,
+}
+
 #[derive(Debug)]
 pub struct DamageCommand {
     pub entity: Entity,
     pub amount: i32,
+    pub damage_type: DamageType,
+    /// Who dealt the damage, for kill counts and cause of death
+    pub source: Option<Entity>,
 }
 
 #[derive(Debug)]
@@ -103,6 +320,51 @@ pub struct InInventory {
     pub owner: Entity,
 }
 
+/// Item which merges with items of the same `kind` in an inventory, counting them in `quantity`
+#[derive(Debug, Clone, Copy)]
+pub struct Stack {
+    pub kind: StackKind,
+    pub quantity: i32,
+}
+
+/// Every kind of item which stacks. Each has a spawn function in `spawner::stack_item`, which
+/// items are split off stacks with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackKind {
+    HealthPotion,
+    TelepathyPotion,
+    MagicMappingScroll,
+    IdentifyScroll,
+    RemoveCurseScroll,
+}
+
+/// How heavy an item is. Stacks weigh this much for each item in them.
+#[derive(Debug, Clone, Copy)]
+pub struct Weight {
+    pub pounds: f32,
+}
+
+/// How much an entity can carry. Carrying more than `max_weight` encumbers it.
+#[derive(Debug, Clone, Copy)]
+pub struct InventoryCapacity {
+    pub max_slots: usize,
+    pub max_weight: f32,
+}
+
+/// Carrying more than its `InventoryCapacity` allows, so it only acts every other turn
+#[derive(Debug)]
+pub struct Encumbered {
+    pub is_catching_breath: bool,
+}
+
+impl Encumbered {
+    /// Advance to the next turn, returning whether the carrier can act in it
+    pub fn next_turn(&mut self) -> bool {
+        self.is_catching_breath = !self.is_catching_breath;
+        !self.is_catching_breath
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct PickupItemCommand {
     pub collector: Entity,
@@ -120,3 +382,37 @@ pub struct DropItemCommand {
     pub dropper: Entity,
     pub item: Entity,
 }
+
+/// Throw `item` from the thrower's inventory towards `target`
+#[derive(Debug, Clone, Copy)]
+pub struct ThrowItemCommand {
+    pub thrower: Entity,
+    pub item: Entity,
+    pub target: Position,
+}
+
+#[derive(Debug)]
+pub enum AutoMoveMode {
+    /// Follow a planned path. Remaining steps are stored last step first.
+    Travel(Vec<Point>),
+    /// Walk towards the nearest unexplored tile
+    Explore,
+    /// Keep moving in one direction until something interesting happens. `open_neighbors` is the
+    /// number of walkable tiles around the previous step, used to notice branches and openings.
+    Run {
+        dx: i32,
+        dy: i32,
+        open_neighbors: Option<usize>,
+    },
+}
+
+/// Multi-turn movement, e.g. from clicking on a tile. Removed from the player when the movement is
+/// finished or interrupted.
+#[derive(Debug)]
+pub struct AutoMove {
+    pub mode: AutoMoveMode,
+    pub known_monsters: Vec<Entity>,
+    pub known_items: Vec<Entity>,
+    pub hp: i32,
+    pub combat_message_count: usize,
+}
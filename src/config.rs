@@ -1,12 +1,21 @@
 use serde::{Deserialize, Serialize};
-use std::convert::TryFrom;
+use std::convert::{TryFrom, TryInto};
 
 use crate::prelude::*;
 
+/// Version of the config file format written by this build.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Migrations applied to older config files, where `MIGRATIONS[i]` upgrades a config table from
+/// version `i` to version `i + 1`.
+const MIGRATIONS: &[fn(&mut toml::value::Table)] = &[migrate_v0_to_v1];
+
 pub fn default_user_config() -> UserConfig {
     // Colors: https://lospec.com/palette-list/vinik24
 
     UserConfig {
+        config_version: CONFIG_VERSION,
+
         post_scanlines: false,
         post_burnin: Some("#8d6268".into()),
 
@@ -28,6 +37,34 @@ pub fn default_user_config() -> UserConfig {
             fg: Some("#c5ccb8".into()),
             bg: Some("#433455".into()),
         }),
+        ui_travel_path: Some(TextUserConfig {
+            fg: None,
+            bg: Some("#416aa3".into()),
+        }),
+        ui_look_cursor: Some(TextUserConfig {
+            fg: None,
+            bg: Some("#8b5580".into()),
+        }),
+        ui_target_path: Some(TextUserConfig {
+            fg: None,
+            bg: Some("#6b3e75".into()),
+        }),
+        ui_detected: Some(TextUserConfig {
+            fg: Some("#b45252".into()),
+            bg: None,
+        }),
+        particle_hit: Some(TextUserConfig {
+            fg: None,
+            bg: Some("#b45252".into()),
+        }),
+        particle_damage: Some(TextUserConfig {
+            fg: Some("#ead4aa".into()),
+            bg: None,
+        }),
+        particle_heal: Some(TextUserConfig {
+            fg: Some("#63c74d".into()),
+            bg: None,
+        }),
 
         player: TileUserConfig {
             glyph: '@',
@@ -50,6 +87,13 @@ pub fn default_user_config() -> UserConfig {
             bg: None,
             fog_bg: None,
         },
+        down_stairs: TileUserConfig {
+            glyph: '>',
+            fg: Some("#c5ccb8".into()),
+            fog_fg: Some("#6f6776".into()),
+            bg: None,
+            fog_bg: None,
+        },
         orc: TileUserConfig {
             glyph: 'o',
             fg: Some("#9a4f50".into()),
@@ -64,6 +108,41 @@ pub fn default_user_config() -> UserConfig {
             bg: None,
             fog_bg: None,
         },
+        goblin_archer: TileUserConfig {
+            glyph: 'g',
+            fg: Some("#c2a35b".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        orc_shaman: TileUserConfig {
+            glyph: 'o',
+            fg: Some("#8b5580".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        brazier: TileUserConfig {
+            glyph: '☼',
+            fg: Some("#d3a068".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        vendor: TileUserConfig {
+            glyph: '@',
+            fg: Some("#a8ca58".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        villager: TileUserConfig {
+            glyph: 'v',
+            fg: Some("#7ca1c0".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
 
         health_potion: TileUserConfig {
             glyph: '¡',
@@ -72,16 +151,132 @@ pub fn default_user_config() -> UserConfig {
             bg: None,
             fog_bg: None,
         },
+        telepathy_potion: TileUserConfig {
+            glyph: '¡',
+            fg: Some("#7ca1c0".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        magic_mapping_scroll: TileUserConfig {
+            glyph: '?',
+            fg: Some("#c5ccb8".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        identify_scroll: TileUserConfig {
+            glyph: '?',
+            fg: Some("#b8c5cc".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        remove_curse_scroll: TileUserConfig {
+            glyph: '?',
+            fg: Some("#ccc5b8".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        detection_amulet: TileUserConfig {
+            glyph: '"',
+            fg: Some("#d3a068".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        dagger: TileUserConfig {
+            glyph: '/',
+            fg: Some("#b0b8c0".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        shield: TileUserConfig {
+            glyph: '[',
+            fg: Some("#a08060".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        gold: TileUserConfig {
+            glyph: '$',
+            fg: Some("#e8c170".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        corpse: TileUserConfig {
+            glyph: '%',
+            fg: Some("#a06050".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        blood: TileUserConfig {
+            glyph: '.',
+            fg: Some("#8a1010".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        arrow: TileUserConfig {
+            glyph: ')',
+            fg: Some("#e3cfb4".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
+        magic_bolt: TileUserConfig {
+            glyph: '*',
+            fg: Some("#c880d8".into()),
+            fog_fg: None,
+            bg: None,
+            fog_bg: None,
+        },
     }
 }
 
 #[derive(Error, Debug)]
 pub enum ConfigParseError {
-    #[error("Could not convert '{0}' to CP437")]
-    UnrecognizedGlyph(char),
+    #[error("Error parsing TOML: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error(
+        "Unsupported config version {0} (latest supported version: {})",
+        CONFIG_VERSION
+    )]
+    UnsupportedVersion(i64),
 
-    #[error("Error parsing color code (expected format: \"#123abc\", got: \"{0}\")")]
-    BadColorCode(String),
+    #[error("{0}: Could not convert '{1}' to CP437")]
+    UnrecognizedGlyph(String, char),
+
+    #[error("{0}: Error parsing color code (expected format: \"#123abc\", got: \"{1}\")")]
+    BadColorCode(String, String),
+}
+
+/// Every problem found while loading a config, so they can all be reported at once.
+#[derive(Error, Debug)]
+pub struct ConfigErrors(pub Vec<ConfigParseError>);
+
+impl Display for ConfigErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, err) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            err.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ConfigParseError> for ConfigErrors {
+    fn from(err: ConfigParseError) -> Self {
+        ConfigErrors(vec![err])
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -101,6 +296,8 @@ pub struct TextUserConfig {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserConfig {
+    pub config_version: u32,
+
     pub post_scanlines: bool,
     pub post_burnin: Option<String>,
 
@@ -113,14 +310,39 @@ pub struct UserConfig {
     pub ui_title: Option<TextUserConfig>,
     pub ui_hp_bar: Option<TextUserConfig>,
     pub ui_tooltip: Option<TextUserConfig>,
+    pub ui_travel_path: Option<TextUserConfig>,
+    pub ui_look_cursor: Option<TextUserConfig>,
+    pub ui_target_path: Option<TextUserConfig>,
+    pub ui_detected: Option<TextUserConfig>,
+    pub particle_hit: Option<TextUserConfig>,
+    pub particle_damage: Option<TextUserConfig>,
+    pub particle_heal: Option<TextUserConfig>,
 
     pub player: TileUserConfig,
     pub wall: TileUserConfig,
     pub floor: TileUserConfig,
+    pub down_stairs: TileUserConfig,
     pub orc: TileUserConfig,
     pub goblin: TileUserConfig,
+    pub goblin_archer: TileUserConfig,
+    pub orc_shaman: TileUserConfig,
+    pub brazier: TileUserConfig,
+    pub vendor: TileUserConfig,
+    pub villager: TileUserConfig,
 
     pub health_potion: TileUserConfig,
+    pub telepathy_potion: TileUserConfig,
+    pub magic_mapping_scroll: TileUserConfig,
+    pub identify_scroll: TileUserConfig,
+    pub remove_curse_scroll: TileUserConfig,
+    pub detection_amulet: TileUserConfig,
+    pub dagger: TileUserConfig,
+    pub shield: TileUserConfig,
+    pub gold: TileUserConfig,
+    pub corpse: TileUserConfig,
+    pub blood: TileUserConfig,
+    pub arrow: TileUserConfig,
+    pub magic_bolt: TileUserConfig,
 }
 
 #[derive(Debug, Clone)]
@@ -133,10 +355,12 @@ pub struct TileConfig {
 }
 
 impl TileConfig {
-    fn try_from_user_config(
+    fn from_user_config(
         value: TileUserConfig,
         defaults: &TileConfig,
-    ) -> Result<Self, ConfigParseError> {
+        field: &str,
+        parser: &mut ConfigParser,
+    ) -> Self {
         let TileUserConfig {
             glyph,
             fg,
@@ -145,13 +369,13 @@ impl TileConfig {
             fog_bg,
         } = value;
 
-        Ok(TileConfig {
-            glyph: parse_glyph(glyph)?,
-            fg: parse_color_code_option(fg, defaults.fg)?,
-            fog_fg: parse_color_code_option(fog_fg, defaults.fog_fg)?,
-            bg: parse_color_code_option(bg, defaults.bg)?,
-            fog_bg: parse_color_code_option(fog_bg, defaults.fog_bg)?,
-        })
+        TileConfig {
+            glyph: parser.glyph(format!("{}.glyph", field), glyph),
+            fg: parser.color_option(format!("{}.fg", field), fg, defaults.fg),
+            fog_fg: parser.color_option(format!("{}.fog_fg", field), fog_fg, defaults.fog_fg),
+            bg: parser.color_option(format!("{}.bg", field), bg, defaults.bg),
+            fog_bg: parser.color_option(format!("{}.fog_bg", field), fog_bg, defaults.fog_bg),
+        }
     }
 
     pub fn to_renderable_with_render_order(&self, render_order: i32) -> Renderable {
@@ -159,6 +383,8 @@ impl TileConfig {
             glyph: self.glyph,
             fg: self.fg,
             bg: self.bg,
+            fog_fg: self.fog_fg,
+            fog_bg: self.fog_bg,
             render_order,
         }
     }
@@ -171,20 +397,22 @@ pub struct TextConfig {
 }
 
 impl TextConfig {
-    fn try_from_option_user_config(
+    fn from_option_user_config(
         value: Option<TextUserConfig>,
         defaults: &TextConfig,
-    ) -> Result<Self, ConfigParseError> {
+        field: &str,
+        parser: &mut ConfigParser,
+    ) -> Self {
         match value {
             Some(value) => {
                 let TextUserConfig { fg, bg } = value;
 
-                Ok(TextConfig {
-                    fg: parse_color_code_option(fg, defaults.fg)?,
-                    bg: parse_color_code_option(bg, defaults.bg)?,
-                })
+                TextConfig {
+                    fg: parser.color_option(format!("{}.fg", field), fg, defaults.fg),
+                    bg: parser.color_option(format!("{}.bg", field), bg, defaults.bg),
+                }
             }
-            None => Ok(defaults.clone()),
+            None => defaults.clone(),
         }
     }
 }
@@ -198,19 +426,45 @@ pub struct Config {
     pub ui_title: TextConfig,
     pub ui_hp_bar: TextConfig,
     pub ui_tooltip: TextConfig,
+    pub ui_travel_path: TextConfig,
+    pub ui_look_cursor: TextConfig,
+    pub ui_target_path: TextConfig,
+    pub ui_detected: TextConfig,
+    pub particle_hit: TextConfig,
+    pub particle_damage: TextConfig,
+    pub particle_heal: TextConfig,
     pub player: TileConfig,
     pub wall: TileConfig,
     pub floor: TileConfig,
+    pub down_stairs: TileConfig,
     pub orc: TileConfig,
     pub goblin: TileConfig,
+    pub goblin_archer: TileConfig,
+    pub orc_shaman: TileConfig,
+    pub brazier: TileConfig,
+    pub vendor: TileConfig,
+    pub villager: TileConfig,
     pub health_potion: TileConfig,
+    pub telepathy_potion: TileConfig,
+    pub magic_mapping_scroll: TileConfig,
+    pub identify_scroll: TileConfig,
+    pub remove_curse_scroll: TileConfig,
+    pub detection_amulet: TileConfig,
+    pub dagger: TileConfig,
+    pub shield: TileConfig,
+    pub gold: TileConfig,
+    pub corpse: TileConfig,
+    pub blood: TileConfig,
+    pub arrow: TileConfig,
+    pub magic_bolt: TileConfig,
 }
 
 impl TryFrom<UserConfig> for Config {
-    type Error = ConfigParseError;
+    type Error = ConfigErrors;
 
     fn try_from(value: UserConfig) -> Result<Self, Self::Error> {
         let UserConfig {
+            config_version: _,
             post_scanlines,
             post_burnin,
             default_fg,
@@ -218,23 +472,50 @@ impl TryFrom<UserConfig> for Config {
             default_bg,
             default_fog_bg,
             ui,
-            ui_title: ui_hp,
+            ui_title,
             ui_hp_bar,
             ui_tooltip,
+            ui_travel_path,
+            ui_look_cursor,
+            ui_target_path,
+            ui_detected,
+            particle_hit,
+            particle_damage,
+            particle_heal,
             player,
             wall,
             floor,
+            down_stairs,
             orc,
             goblin,
+            goblin_archer,
+            orc_shaman,
+            brazier,
+            vendor,
+            villager,
             health_potion,
+            telepathy_potion,
+            magic_mapping_scroll,
+            identify_scroll,
+            remove_curse_scroll,
+            detection_amulet,
+            dagger,
+            shield,
+            gold,
+            corpse,
+            blood,
+            arrow,
+            magic_bolt,
         } = value;
 
+        let mut parser = ConfigParser::default();
+
         let tile_defaults = TileConfig {
             glyph: 0,
-            fg: parse_color_code(default_fg)?,
-            fog_fg: parse_color_code(default_fog_fg)?,
-            bg: parse_color_code(default_bg)?,
-            fog_bg: parse_color_code(default_fog_bg)?,
+            fg: parser.color("default_fg", default_fg),
+            fog_fg: parser.color("default_fog_fg", default_fog_fg),
+            bg: parser.color("default_bg", default_bg),
+            fog_bg: parser.color("default_fog_bg", default_fog_bg),
         };
 
         let text_defaults = TextConfig {
@@ -242,50 +523,248 @@ impl TryFrom<UserConfig> for Config {
             bg: tile_defaults.bg,
         };
 
-        let post_burnin = match post_burnin {
-            Some(s) => Some(parse_color_code(s)?),
-            None => None,
-        };
+        let post_burnin = post_burnin.map(|s| parser.color("post_burnin", s));
+
+        let p = &mut parser;
 
-        Ok(Config {
+        let config = Config {
             post_scanlines,
             post_burnin,
             bg: tile_defaults.bg,
-            ui: TextConfig::try_from_option_user_config(ui, &text_defaults)?,
-            ui_title: TextConfig::try_from_option_user_config(ui_hp, &text_defaults)?,
-            ui_hp_bar: TextConfig::try_from_option_user_config(ui_hp_bar, &text_defaults)?,
-            ui_tooltip: TextConfig::try_from_option_user_config(ui_tooltip, &text_defaults)?,
-            player: TileConfig::try_from_user_config(player, &tile_defaults)?,
-            wall: TileConfig::try_from_user_config(wall, &tile_defaults)?,
-            floor: TileConfig::try_from_user_config(floor, &tile_defaults)?,
-            orc: TileConfig::try_from_user_config(orc, &tile_defaults)?,
-            goblin: TileConfig::try_from_user_config(goblin, &tile_defaults)?,
-            health_potion: TileConfig::try_from_user_config(health_potion, &tile_defaults)?,
-        })
+            ui: TextConfig::from_option_user_config(ui, &text_defaults, "ui", p),
+            ui_title: TextConfig::from_option_user_config(ui_title, &text_defaults, "ui_title", p),
+            ui_hp_bar: TextConfig::from_option_user_config(
+                ui_hp_bar,
+                &text_defaults,
+                "ui_hp_bar",
+                p,
+            ),
+            ui_tooltip: TextConfig::from_option_user_config(
+                ui_tooltip,
+                &text_defaults,
+                "ui_tooltip",
+                p,
+            ),
+            ui_travel_path: TextConfig::from_option_user_config(
+                ui_travel_path,
+                &text_defaults,
+                "ui_travel_path",
+                p,
+            ),
+            ui_look_cursor: TextConfig::from_option_user_config(
+                ui_look_cursor,
+                &text_defaults,
+                "ui_look_cursor",
+                p,
+            ),
+            ui_target_path: TextConfig::from_option_user_config(
+                ui_target_path,
+                &text_defaults,
+                "ui_target_path",
+                p,
+            ),
+            ui_detected: TextConfig::from_option_user_config(
+                ui_detected,
+                &text_defaults,
+                "ui_detected",
+                p,
+            ),
+            particle_hit: TextConfig::from_option_user_config(
+                particle_hit,
+                &text_defaults,
+                "particle_hit",
+                p,
+            ),
+            particle_damage: TextConfig::from_option_user_config(
+                particle_damage,
+                &text_defaults,
+                "particle_damage",
+                p,
+            ),
+            particle_heal: TextConfig::from_option_user_config(
+                particle_heal,
+                &text_defaults,
+                "particle_heal",
+                p,
+            ),
+            player: TileConfig::from_user_config(player, &tile_defaults, "player", p),
+            wall: TileConfig::from_user_config(wall, &tile_defaults, "wall", p),
+            floor: TileConfig::from_user_config(floor, &tile_defaults, "floor", p),
+            down_stairs: TileConfig::from_user_config(
+                down_stairs,
+                &tile_defaults,
+                "down_stairs",
+                p,
+            ),
+            orc: TileConfig::from_user_config(orc, &tile_defaults, "orc", p),
+            goblin: TileConfig::from_user_config(goblin, &tile_defaults, "goblin", p),
+            goblin_archer: TileConfig::from_user_config(
+                goblin_archer,
+                &tile_defaults,
+                "goblin_archer",
+                p,
+            ),
+            orc_shaman: TileConfig::from_user_config(orc_shaman, &tile_defaults, "orc_shaman", p),
+            brazier: TileConfig::from_user_config(brazier, &tile_defaults, "brazier", p),
+            vendor: TileConfig::from_user_config(vendor, &tile_defaults, "vendor", p),
+            villager: TileConfig::from_user_config(villager, &tile_defaults, "villager", p),
+            health_potion: TileConfig::from_user_config(
+                health_potion,
+                &tile_defaults,
+                "health_potion",
+                p,
+            ),
+            telepathy_potion: TileConfig::from_user_config(
+                telepathy_potion,
+                &tile_defaults,
+                "telepathy_potion",
+                p,
+            ),
+            magic_mapping_scroll: TileConfig::from_user_config(
+                magic_mapping_scroll,
+                &tile_defaults,
+                "magic_mapping_scroll",
+                p,
+            ),
+            identify_scroll: TileConfig::from_user_config(
+                identify_scroll,
+                &tile_defaults,
+                "identify_scroll",
+                p,
+            ),
+            remove_curse_scroll: TileConfig::from_user_config(
+                remove_curse_scroll,
+                &tile_defaults,
+                "remove_curse_scroll",
+                p,
+            ),
+            detection_amulet: TileConfig::from_user_config(
+                detection_amulet,
+                &tile_defaults,
+                "detection_amulet",
+                p,
+            ),
+            dagger: TileConfig::from_user_config(dagger, &tile_defaults, "dagger", p),
+            shield: TileConfig::from_user_config(shield, &tile_defaults, "shield", p),
+            gold: TileConfig::from_user_config(gold, &tile_defaults, "gold", p),
+            corpse: TileConfig::from_user_config(corpse, &tile_defaults, "corpse", p),
+            blood: TileConfig::from_user_config(blood, &tile_defaults, "blood", p),
+            arrow: TileConfig::from_user_config(arrow, &tile_defaults, "arrow", p),
+            magic_bolt: TileConfig::from_user_config(magic_bolt, &tile_defaults, "magic_bolt", p),
+        };
+
+        if parser.errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigErrors(parser.errors))
+        }
     }
 }
 
-fn parse_glyph(glyph: char) -> Result<FontCharType, ConfigParseError> {
-    match to_cp437(glyph) {
-        0 => Err(ConfigParseError::UnrecognizedGlyph(glyph)),
-        x => Ok(x),
+/// Parse a (possibly partial or outdated) TOML config. Fields missing from `s` are taken from
+/// `default_user_config()`.
+pub fn user_config_from_str(s: &str) -> Result<UserConfig, ConfigParseError> {
+    let mut user_table: toml::value::Table = toml::from_str(s)?;
+    migrate_user_config(&mut user_table)?;
+
+    let mut merged =
+        toml::Value::try_from(default_user_config()).expect("Default config is serializable");
+    merge_toml(&mut merged, toml::Value::Table(user_table));
+
+    Ok(merged.try_into()?)
+}
+
+/// Parse and validate a TOML config, collecting every problem found instead of stopping at the
+/// first one.
+pub fn config_from_str(s: &str) -> Result<Config, ConfigErrors> {
+    user_config_from_str(s)?.try_into()
+}
+
+fn migrate_user_config(table: &mut toml::value::Table) -> Result<(), ConfigParseError> {
+    // Configs written before `config_version` existed are version 0
+    let version = table
+        .get("config_version")
+        .and_then(toml::Value::as_integer)
+        .unwrap_or(0);
+
+    if version < 0 || version > CONFIG_VERSION as i64 {
+        return Err(ConfigParseError::UnsupportedVersion(version));
+    }
+
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        migration(table);
     }
+
+    table.insert(
+        "config_version".into(),
+        toml::Value::Integer(CONFIG_VERSION as i64),
+    );
+
+    Ok(())
 }
 
-fn parse_color_code(code: String) -> Result<RGB, ConfigParseError> {
-    match RGB::from_hex(&code) {
-        Ok(rgb) => Ok(rgb),
-        Err(_) => Err(ConfigParseError::BadColorCode(code)),
+fn migrate_v0_to_v1(_table: &mut toml::value::Table) {
+    // Version 0 only differs by the missing `config_version` field. Any tiles added since then are
+    // filled in from the defaults by `merge_toml`.
+}
+
+/// Recursively merge `overlay` into `base`. Tables are merged key by key, any other value in
+/// `overlay` replaces the one in `base`.
+fn merge_toml(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge_toml(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
     }
 }
 
-fn parse_color_code_option(
-    code_option: Option<String>,
-    default: RGB,
-) -> Result<RGB, ConfigParseError> {
-    match code_option {
-        Some(code) => parse_color_code(code),
-        None => Ok(default),
+/// Collects parse errors so that every bad field in a config is reported, not just the first.
+#[derive(Default)]
+struct ConfigParser {
+    errors: Vec<ConfigParseError>,
+}
+
+impl ConfigParser {
+    fn glyph<S: Into<String>>(&mut self, field: S, glyph: char) -> FontCharType {
+        match to_cp437(glyph) {
+            0 => {
+                self.errors
+                    .push(ConfigParseError::UnrecognizedGlyph(field.into(), glyph));
+                0
+            }
+            x => x,
+        }
+    }
+
+    fn color<S: Into<String>>(&mut self, field: S, code: String) -> RGB {
+        match RGB::from_hex(&code) {
+            Ok(rgb) => rgb,
+            Err(_) => {
+                self.errors
+                    .push(ConfigParseError::BadColorCode(field.into(), code));
+                RGB::new()
+            }
+        }
+    }
+
+    fn color_option<S: Into<String>>(
+        &mut self,
+        field: S,
+        code_option: Option<String>,
+        default: RGB,
+    ) -> RGB {
+        match code_option {
+            Some(code) => self.color(field, code),
+            None => default,
+        }
     }
 }
 
@@ -300,4 +779,43 @@ mod tests {
         let res: Result<Config, _> = default_user_config().try_into();
         assert!(res.is_ok());
     }
+
+    #[test]
+    fn test_default_config_roundtrip() {
+        let s = toml::to_string_pretty(&default_user_config()).unwrap();
+        assert!(config_from_str(&s).is_ok());
+    }
+
+    #[test]
+    fn test_partial_config_merges_defaults() {
+        let config = config_from_str("config_version = 1\n[wall]\nglyph = \"X\"\n").unwrap();
+        let defaults: Config = default_user_config().try_into().unwrap();
+
+        assert_eq!(config.wall.glyph, to_cp437('X'));
+        assert_eq!(config.wall.fg, defaults.wall.fg);
+        assert_eq!(config.floor.glyph, defaults.floor.glyph);
+    }
+
+    #[test]
+    fn test_unversioned_config_is_migrated() {
+        let user_config = user_config_from_str("post_scanlines = true\n").unwrap();
+        assert_eq!(user_config.config_version, CONFIG_VERSION);
+        assert!(user_config.post_scanlines);
+    }
+
+    #[test]
+    fn test_newer_config_version_is_rejected() {
+        let res = user_config_from_str(&format!("config_version = {}\n", CONFIG_VERSION + 1));
+        assert!(matches!(res, Err(ConfigParseError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn test_every_problem_is_reported() {
+        let res = config_from_str(
+            "default_fg = \"red\"\n[wall]\nglyph = \"\u{2603}\"\nfg = \"#12345\"\n",
+        );
+
+        let ConfigErrors(errors) = res.unwrap_err();
+        assert_eq!(errors.len(), 3);
+    }
 }
@@ -1,14 +1,27 @@
-use std::collections::HashSet;
+use crate::{
+    combat_rules::{damage_modifier, DamageModifier},
+    despawn_entities_system::queue_despawn_batch,
+    map_indexing_system::unblock_tile,
+    morgue::RunStats,
+    particle::{spawn_hit_flash, spawn_number},
+    prelude::*,
+    spawner,
+};
 
-use crate::{despawn_entities_system::queue_despawn_batch, prelude::*};
-
-pub fn damage_system(world: &mut World) -> anyhow::Result<()> {
-    let mut despawn_entities = HashSet::new();
+pub fn damage_system(world: &mut World, config: &Config) -> anyhow::Result<()> {
+    // Kept in order, so replays despawn entities in the same order every time
+    let mut despawn_entities = Vec::new();
+    let mut bleeding = Vec::new();
+    let mut hurt = Vec::new();
+    let mut poisoned = Vec::new();
 
     {
         let player_entity = world.resource_entity::<Player>().ok();
 
         for (_, cmd) in world.query::<&DamageCommand>().into_iter() {
+            let modifier = damage_modifier_for(world, cmd.entity, cmd.damage_type);
+            let amount = modifier.apply(cmd.amount);
+
             let mut stats = match world.query_one::<&mut CombatStats>(cmd.entity) {
                 Ok(stats) => stats,
                 Err(_err) => {
@@ -21,17 +34,71 @@ pub fn damage_system(world: &mut World) -> anyhow::Result<()> {
             };
             let stats = stats.get().expect("Unfiltered query");
 
-            stats.hp = i32::max(0, stats.hp - cmd.amount);
+            let was_alive = stats.hp > 0;
+
+            stats.hp = i32::max(0, stats.hp - amount);
+
+            if amount > 0 {
+                hurt.push((cmd.entity, amount));
+
+                if let Ok(pos) = world.get::<Position>(cmd.entity) {
+                    bleeding.push(*pos);
+                }
+            }
 
-            if stats.hp <= 0 {
-                despawn_entities.insert(cmd.entity);
+            if let Some(message) = resistance_message(
+                world,
+                cmd.entity,
+                Some(cmd.entity) == player_entity,
+                modifier,
+                cmd.damage_type,
+            ) {
+                GameLog::resource_push_combat(world, message)?;
+            }
+
+            // Poisoned weapons only poison what they wound
+            if amount > 0 && stats.hp > 0 && cmd.damage_type == DamageType::Physical {
+                let poisons = cmd
+                    .source
+                    .and_then(|source| world.get::<PoisonsOnHit>(source).ok().map(|p| *p));
+
+                if let Some(poisons) = poisons {
+                    poisoned.push((cmd.entity, poisons, cmd.source));
+                }
+            }
+
+            if was_alive && stats.hp <= 0 {
+                // Tougher monsters are worth more experience
+                let xp_value = stats.max_hp.max(0) as u32;
+                let killer_name = cmd
+                    .source
+                    .and_then(|source| world.get::<Name>(source).ok().map(|name| name.to_string()));
 
                 if Some(cmd.entity) == player_entity {
-                    GameLog::resource_push(world, "You are dead!")?;
+                    // The player is kept around for the morgue file
+                    GameLog::resource_push_combat(world, "You are dead!")?;
+
+                    world.resource::<RunStats>()?.map(|mut run_stats| {
+                        run_stats.cause_of_death = Some(match (killer_name, cmd.damage_type) {
+                            (Some(name), DamageType::Poison) => format!("Poisoned by {}", name),
+                            (Some(name), _) => format!("Killed by {}", name),
+                            (None, DamageType::Poison) => "Succumbed to poison".to_string(),
+                            (None, _) => "Died".to_string(),
+                        });
+                    })?;
                 } else {
+                    despawn_entities.push(cmd.entity);
+
                     if let Ok(mut q) = world.query_one::<&Name>(cmd.entity) {
                         if let Some(Name(name)) = q.get() {
-                            GameLog::resource_push(world, format!("{} was slain!", name))?;
+                            GameLog::resource_push_combat(world, format!("{} was slain!", name))?;
+
+                            if cmd.source.is_some() && cmd.source == player_entity {
+                                world.resource::<RunStats>()?.map(|mut run_stats| {
+                                    run_stats.xp += xp_value;
+                                    *run_stats.kills.entry(name.clone()).or_insert(0) += 1;
+                                })?;
+                            }
                         }
                     }
                 }
@@ -39,7 +106,249 @@ pub fn damage_system(world: &mut World) -> anyhow::Result<()> {
         }
     }
 
+    for (entity, poisons, source) in poisoned {
+        poison(world, entity, poisons, source)?;
+    }
+
+    for (entity, amount) in hurt {
+        spawn_hit_flash(world, config, entity);
+        spawn_number(
+            world,
+            entity,
+            &amount.to_string(),
+            config.particle_damage.fg,
+        );
+    }
+
+    for entity in despawn_entities.iter().copied() {
+        drop_loot(world, config, entity)?;
+        leave_corpse(world, config, entity)?;
+    }
+
+    for pos in bleeding {
+        spawner::blood(world, config, pos.x, pos.y);
+    }
+
     queue_despawn_batch(world, despawn_entities);
 
     Ok(())
 }
+
+/// How `entity`'s resistances, vulnerabilities and immunities change the damage it takes of
+/// `damage_type`
+fn damage_modifier_for(world: &World, entity: Entity, damage_type: DamageType) -> DamageModifier {
+    let resistant = world
+        .get::<Resistant>(entity)
+        .map(|resistant| resistant.0.contains(&damage_type))
+        .unwrap_or(false);
+    let vulnerable = world
+        .get::<Vulnerable>(entity)
+        .map(|vulnerable| vulnerable.0.contains(&damage_type))
+        .unwrap_or(false);
+    let immune = world
+        .get::<Immune>(entity)
+        .map(|immune| immune.0.contains(&damage_type))
+        .unwrap_or(false);
+
+    damage_modifier(resistant, vulnerable, immune)
+}
+
+/// "Orc resists the poison.", "You are vulnerable to fire!" and so on, or `None` if the damage
+/// wasn't modified
+fn resistance_message(
+    world: &World,
+    entity: Entity,
+    is_player: bool,
+    modifier: DamageModifier,
+    damage_type: DamageType,
+) -> Option<String> {
+    let (subject, resists, is) = if is_player {
+        ("You".to_string(), "resist", "are")
+    } else {
+        let name = world.get::<Name>(entity).ok()?.to_string();
+        (name, "resists", "is")
+    };
+    let damage = damage_type.name();
+
+    match modifier {
+        DamageModifier::Normal => None,
+        DamageModifier::Resisted => Some(format!("{} {} the {}.", subject, resists, damage)),
+        DamageModifier::Vulnerable => Some(format!("{} {} vulnerable to {}!", subject, is, damage)),
+        DamageModifier::Immune => Some(format!("{} {} immune to {}.", subject, is, damage)),
+    }
+}
+
+/// Poison `entity` unless it is immune, replacing any poison already in its veins
+fn poison(
+    world: &mut World,
+    entity: Entity,
+    poisons: PoisonsOnHit,
+    source: Option<Entity>,
+) -> anyhow::Result<()> {
+    if damage_modifier_for(world, entity, DamageType::Poison) == DamageModifier::Immune {
+        return Ok(());
+    }
+
+    world.insert_one(
+        entity,
+        Poisoned {
+            turns_left: poisons.turns,
+            damage: poisons.damage,
+            source,
+        },
+    )?;
+
+    if Some(entity) == world.resource_entity::<Player>().ok() {
+        GameLog::resource_push_combat(world, "You are poisoned!")?;
+    } else if let Ok(name) = world.get::<Name>(entity).map(|name| name.to_string()) {
+        GameLog::resource_push_combat(world, format!("{} is poisoned.", name))?;
+    }
+
+    Ok(())
+}
+
+/// Replace a dying entity with its corpse, which doesn't block its tile
+fn leave_corpse(world: &mut World, config: &Config, entity: Entity) -> anyhow::Result<()> {
+    let pos = match world.get::<Position>(entity) {
+        Ok(pos) => *pos,
+        Err(_) => return Ok(()),
+    };
+
+    let name = world.get::<Name>(entity)?.to_string();
+
+    // The entity is only despawned during cleanup, so free its tile for anything moving before then
+    unblock_tile(world, entity);
+
+    spawner::corpse(world, config, &name, pos.x, pos.y);
+
+    Ok(())
+}
+
+/// Drop everything a dying entity carries where it stands, plus whatever it rolls on its loot table
+fn drop_loot(world: &mut World, config: &Config, entity: Entity) -> anyhow::Result<()> {
+    let pos = match world.get::<Position>(entity) {
+        Ok(pos) => *pos,
+        Err(_) => return Ok(()),
+    };
+
+    let carried = world
+        .query::<&InInventory>()
+        .into_iter()
+        .filter(|(_, in_inventory)| in_inventory.owner == entity)
+        .map(|(item, _)| item)
+        .collect::<Vec<_>>();
+
+    let mut drop_commands = Vec::new();
+
+    for item in carried {
+        // Curses don't outlive their wearer
+        let _ = world.remove_one::<Equipped>(item);
+
+        drop_commands.push(DropItemCommand {
+            dropper: entity,
+            item,
+        });
+    }
+
+    world.spawn_batch_commands(drop_commands);
+
+    if let Ok(table) = world.get::<LootTable>(entity).map(|table| *table) {
+        spawner::rng_loot(world, config, table, pos.x, pos.y)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::config_from_str, inventory::drop_item_system};
+
+    #[test]
+    fn test_dead_monster_drops_inventory() {
+        let config = config_from_str("").unwrap();
+
+        let mut world = World::new();
+        world.spawn_resource(GameLog::new(), ()).unwrap();
+
+        let monster = world.spawn((
+            Monster,
+            Name("Orc".into()),
+            Position { x: 3, y: 4 },
+            CombatStats {
+                max_hp: 10,
+                hp: 2,
+                defense: 0,
+                power: 1,
+            },
+        ));
+        let item = world.spawn((
+            Name("Dagger".into()),
+            Item,
+            InInventory { owner: monster },
+            Equipped {
+                owner: monster,
+                slot: EquipmentSlot::Melee,
+            },
+            Cursed,
+        ));
+
+        world.spawn_command(DamageCommand {
+            entity: monster,
+            amount: 5,
+            damage_type: DamageType::Physical,
+            source: None,
+        });
+        damage_system(&mut world, &config).unwrap();
+        drop_item_system(&mut world).unwrap();
+
+        assert_eq!(
+            *world.get::<Position>(item).unwrap(),
+            Position { x: 3, y: 4 }
+        );
+        assert!(world.get::<InInventory>(item).is_err());
+        assert!(world.get::<Equipped>(item).is_err());
+    }
+
+    #[test]
+    fn test_dead_monster_leaves_corpse() {
+        let config = config_from_str("").unwrap();
+
+        let mut world = World::new();
+        world.spawn_resource(GameLog::new(), ()).unwrap();
+
+        let monster = world.spawn((
+            Monster,
+            Name("Orc".into()),
+            Position { x: 3, y: 4 },
+            BlocksTile,
+            CombatStats {
+                max_hp: 10,
+                hp: 2,
+                defense: 0,
+                power: 1,
+            },
+        ));
+
+        world.spawn_command(DamageCommand {
+            entity: monster,
+            amount: 5,
+            damage_type: DamageType::Physical,
+            source: None,
+        });
+        damage_system(&mut world, &config).unwrap();
+
+        assert!(world.get::<BlocksTile>(monster).is_err());
+
+        let corpses = world
+            .query::<(&Corpse, &Name, &Position)>()
+            .into_iter()
+            .map(|(_, (_, name, pos))| (name.to_string(), *pos))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            corpses,
+            vec![("Orc corpse".to_string(), Position { x: 3, y: 4 })]
+        );
+        assert_eq!(world.query::<&Decal>().into_iter().count(), 1);
+    }
+}
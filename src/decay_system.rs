@@ -0,0 +1,62 @@
+use crate::{
+    despawn_entities_system::queue_despawn_batch, identification::display_name, prelude::*,
+};
+
+/// Tick down everything which `Decays`, despawning what has rotted or faded away
+pub fn decay_system(world: &mut World) -> anyhow::Result<()> {
+    if world.resource_clone::<RunState>()? != RunState::PlayerTurn {
+        return Ok(());
+    }
+
+    let player = world.resource_entity::<Player>().ok();
+
+    let mut decayed = Vec::new();
+
+    for (entity, decays) in world.query::<&mut Decays>().into_iter() {
+        decays.turns_left -= 1;
+
+        if decays.turns_left <= 0 {
+            decayed.push(entity);
+        }
+    }
+
+    for entity in decayed.iter().copied() {
+        // Anything carried by the player rots away in their hands
+        let is_carried_by_player = match world.get::<InInventory>(entity) {
+            Ok(in_inventory) => Some(in_inventory.owner) == player,
+            Err(_) => false,
+        };
+
+        if is_carried_by_player {
+            let name = display_name(world, entity).unwrap_or_default();
+            GameLog::resource_push(world, format!("The {} rots away.", name))?;
+        }
+    }
+
+    queue_despawn_batch(world, decayed);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::despawn_entities_system::despawn_entities_system;
+
+    #[test]
+    fn test_decay() {
+        let mut world = World::new();
+        world.spawn_resource(GameLog::new(), ()).unwrap();
+        world.spawn_resource(RunState::PlayerTurn, ()).unwrap();
+
+        let corpse = world.spawn((Name("Orc corpse".into()), Decays { turns_left: 2 }));
+
+        decay_system(&mut world).unwrap();
+        despawn_entities_system(&mut world);
+        assert_eq!(world.get::<Decays>(corpse).unwrap().turns_left, 1);
+
+        decay_system(&mut world).unwrap();
+        despawn_entities_system(&mut world);
+        assert!(!world.contains(corpse));
+    }
+}
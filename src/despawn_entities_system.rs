@@ -1,4 +1,4 @@
-use crate::prelude::*;
+use crate::{map_indexing_system::unindex_entity, prelude::*};
 
 #[derive(Debug)]
 struct DespawnCommand(pub Entity);
@@ -15,6 +15,8 @@ pub fn despawn_entities_system(world: &mut World) {
 
 fn despawn_entities(world: &mut World, entities: Vec<Entity>) {
     for entity in entities {
+        unindex_entity(world, entity);
+
         if let Err(_) = world.despawn(entity) {
             console::log(format!("Tried to despawn missing entity: {}", entity.id()));
         }
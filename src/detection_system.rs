@@ -0,0 +1,76 @@
+use crate::prelude::*;
+
+/// Tick down telepathy, and mark the monsters the player can sense beyond their viewshed as
+/// `Detected`
+pub fn detection_system(world: &mut World) -> anyhow::Result<()> {
+    let player = world.resource_entity::<Player>()?;
+
+    if world.resource_clone::<RunState>()? == RunState::PlayerTurn {
+        tick_telepathy(world, player)?;
+    }
+
+    let is_telepathic = world.get::<Telepathic>(player).is_ok();
+
+    let detection_range = world
+        .query::<(&InInventory, &DetectsMonsters)>()
+        .into_iter()
+        .filter(|(_, (in_inventory, _))| in_inventory.owner == player)
+        .map(|(_, (_, detects))| detects.range)
+        .max();
+
+    let player_pos = world.get::<Position>(player)?.to_point();
+
+    let previously_detected = world
+        .query::<&Detected>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    for entity in previously_detected {
+        world.remove_one::<Detected>(entity)?;
+    }
+
+    let detected = world
+        .query::<(&Monster, &Position)>()
+        .into_iter()
+        .filter(|(_, (_, pos))| {
+            is_telepathic
+                || match detection_range {
+                    Some(range) => {
+                        DistanceAlg::Pythagoras.distance2d(player_pos, pos.to_point())
+                            <= range as f32
+                    }
+                    None => false,
+                }
+        })
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    for entity in detected {
+        world.insert_one(entity, Detected)?;
+    }
+
+    Ok(())
+}
+
+fn tick_telepathy(world: &mut World, player: Entity) -> anyhow::Result<()> {
+    let mut expired = Vec::new();
+
+    for (entity, telepathic) in world.query::<&mut Telepathic>().into_iter() {
+        telepathic.turns_left -= 1;
+
+        if telepathic.turns_left <= 0 {
+            expired.push(entity);
+        }
+    }
+
+    for entity in expired {
+        world.remove_one::<Telepathic>(entity)?;
+
+        if entity == player {
+            GameLog::resource_push(world, "You can no longer sense the minds around you.")?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,485 @@
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    faction::{entity_reaction, Reaction},
+    identification::known_name,
+    inventory::{add_to_inventory, check_capacity, find_inventory_item},
+    prelude::*,
+};
+
+const DIALOGUE_DATA: &str = include_str!("../data/dialogue.toml");
+
+/// Must hold for a choice to be offered
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// The player carries an item with this name
+    HasItem(String),
+    Flag(String),
+    NotFlag(String),
+    /// The speaker's faction reacts to the player this way
+    Reaction(Reaction),
+}
+
+/// Applied when a choice is picked
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    /// The speaker gives the player an item with this name from its inventory
+    GiveItem(String),
+    SetFlag(String),
+    StartQuest(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct DialogueFile {
+    #[serde(default)]
+    dialogue: HashMap<String, DialogueData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DialogueData {
+    start: String,
+    node: Vec<NodeData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NodeData {
+    id: String,
+    text: String,
+    #[serde(default)]
+    choice: Vec<ChoiceData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceData {
+    text: String,
+    next: Option<String>,
+    #[serde(default)]
+    conditions: Vec<Condition>,
+    #[serde(default)]
+    effects: Vec<Effect>,
+}
+
+#[derive(Debug)]
+pub struct Dialogue {
+    start: usize,
+    nodes: Vec<DialogueNode>,
+}
+
+#[derive(Debug)]
+pub struct DialogueNode {
+    pub text: String,
+    pub choices: Vec<DialogueChoice>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DialogueChoice {
+    pub text: String,
+    /// Index of the next node, or `None` to end the conversation
+    pub next: Option<usize>,
+    pub conditions: Vec<Condition>,
+    pub effects: Vec<Effect>,
+}
+
+/// Every dialogue tree, by id
+#[derive(Debug)]
+pub struct DialogueLibrary {
+    dialogues: HashMap<String, Dialogue>,
+}
+
+impl DialogueLibrary {
+    /// The dialogue bundled with the game
+    pub fn load_default() -> anyhow::Result<Self> {
+        Self::from_str(DIALOGUE_DATA).context("Error loading dialogue data")
+    }
+
+    pub fn from_str(s: &str) -> anyhow::Result<Self> {
+        let file: DialogueFile = toml::from_str(s)?;
+
+        let dialogues = file
+            .dialogue
+            .into_iter()
+            .map(|(id, data)| {
+                let dialogue = compile_dialogue(data)
+                    .with_context(|| format!("Error in dialogue '{}'", id))?;
+                Ok((id, dialogue))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { dialogues })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Dialogue> {
+        self.dialogues.get(id)
+    }
+}
+
+/// Resolve node ids to indices, so conversations can be tracked in a `RunState`
+fn compile_dialogue(data: DialogueData) -> anyhow::Result<Dialogue> {
+    let mut node_indices = HashMap::new();
+
+    for (i, node) in data.node.iter().enumerate() {
+        if node_indices.insert(node.id.clone(), i).is_some() {
+            return Err(anyhow!("Duplicate node '{}'", node.id));
+        }
+    }
+
+    let find_node = |id: &str| {
+        node_indices
+            .get(id)
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown node '{}'", id))
+    };
+
+    let start = find_node(&data.start)?;
+
+    let nodes = data
+        .node
+        .into_iter()
+        .map(|node| {
+            let node_id = node.id;
+            let choices = node
+                .choice
+                .into_iter()
+                .map(|choice| {
+                    let next = match &choice.next {
+                        Some(next) => Some(find_node(next).with_context(|| {
+                            format!("Choice '{}' of node '{}'", choice.text, node_id)
+                        })?),
+                        None => None,
+                    };
+
+                    Ok(DialogueChoice {
+                        text: choice.text,
+                        next,
+                        conditions: choice.conditions,
+                        effects: choice.effects,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?;
+
+            Ok(DialogueNode {
+                text: node.text,
+                choices,
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(Dialogue { start, nodes })
+}
+
+/// Flags set and quests started by dialogue choices
+#[derive(Debug, Default)]
+pub struct DialogueState {
+    pub flags: BTreeSet<String>,
+    pub quests: Vec<String>,
+}
+
+/// Start talking to `speaker`, if it has anything to say
+pub fn start_dialogue(world: &World, speaker: Entity) -> Option<RunState> {
+    let dialogue_id = world.get::<Speaker>(speaker).ok()?.dialogue.clone();
+
+    let mut library = world.query::<&DialogueLibrary>();
+    let (_, library) = library.into_iter().next()?;
+    let start = library.get(&dialogue_id)?.start;
+
+    Some(RunState::Dialogue {
+        speaker,
+        node: start,
+    })
+}
+
+fn with_node<R>(
+    world: &World,
+    speaker: Entity,
+    node: usize,
+    f: impl FnOnce(&DialogueNode) -> R,
+) -> Option<R> {
+    let dialogue_id = world.get::<Speaker>(speaker).ok()?.dialogue.clone();
+
+    let mut library = world.query::<&DialogueLibrary>();
+    let (_, library) = library.into_iter().next()?;
+
+    library
+        .get(&dialogue_id)
+        .and_then(|dialogue| dialogue.nodes.get(node))
+        .map(f)
+}
+
+pub fn node_text(world: &World, speaker: Entity, node: usize) -> Option<String> {
+    with_node(world, speaker, node, |node| node.text.clone())
+}
+
+/// The choices at `node` whose conditions hold, in the order they are offered
+pub fn available_choices(world: &World, speaker: Entity, node: usize) -> Vec<DialogueChoice> {
+    let choices = with_node(world, speaker, node, |node| node.choices.clone()).unwrap_or_default();
+
+    choices
+        .into_iter()
+        .filter(|choice| {
+            choice
+                .conditions
+                .iter()
+                .all(|condition| is_condition_met(world, speaker, condition))
+        })
+        .collect()
+}
+
+/// Pick the available choice at `index`, applying its effects. Talking doesn't take a turn.
+pub fn choose(
+    world: &mut World,
+    speaker: Entity,
+    node: usize,
+    index: usize,
+) -> anyhow::Result<RunState> {
+    let choice = match available_choices(world, speaker, node)
+        .into_iter()
+        .nth(index)
+    {
+        Some(choice) => choice,
+        None => return Ok(RunState::Dialogue { speaker, node }),
+    };
+
+    for effect in choice.effects.iter() {
+        apply_effect(world, speaker, effect)?;
+    }
+
+    Ok(match choice.next {
+        Some(next) => RunState::Dialogue {
+            speaker,
+            node: next,
+        },
+        None => RunState::AwaitingInput,
+    })
+}
+
+fn is_condition_met(world: &World, speaker: Entity, condition: &Condition) -> bool {
+    let player = match world.resource_entity::<Player>() {
+        Ok(player) => player,
+        Err(_) => return false,
+    };
+
+    let has_flag = |flag: &str| {
+        world
+            .query::<&DialogueState>()
+            .into_iter()
+            .next()
+            .map(|(_, state)| state.flags.contains(flag))
+            .unwrap_or(false)
+    };
+
+    match condition {
+        Condition::HasItem(item_name) => find_inventory_item(world, player, item_name).is_some(),
+        Condition::Flag(flag) => has_flag(flag),
+        Condition::NotFlag(flag) => !has_flag(flag),
+        Condition::Reaction(reaction) => entity_reaction(world, speaker, player) == *reaction,
+    }
+}
+
+fn apply_effect(world: &mut World, speaker: Entity, effect: &Effect) -> anyhow::Result<()> {
+    let player = world.resource_entity::<Player>()?;
+    let speaker_name = world.get::<Name>(speaker)?.to_string();
+
+    match effect {
+        Effect::GiveItem(item_name) => match find_inventory_item(world, speaker, item_name) {
+            Some(item) => {
+                let known_item_name = known_name(world, item_name);
+
+                match check_capacity(world, player, item) {
+                    Ok(()) => {
+                        add_to_inventory(world, item, player)?;
+                        GameLog::resource_push(
+                            world,
+                            format!("The {} gives you the {}.", speaker_name, known_item_name),
+                        )?;
+                    }
+                    Err(err) => GameLog::resource_push(world, err.message(&known_item_name))?,
+                }
+            }
+            None => {
+                GameLog::resource_push(
+                    world,
+                    format!(
+                        "The {} has no {} to give.",
+                        speaker_name,
+                        known_name(world, item_name)
+                    ),
+                )?;
+            }
+        },
+        Effect::SetFlag(flag) => {
+            world.resource::<DialogueState>()?.map(|mut state| {
+                state.flags.insert(flag.clone());
+            })?;
+        }
+        Effect::StartQuest(quest) => {
+            let is_new = world.resource::<DialogueState>()?.map(|mut state| {
+                let is_new = !state.quests.contains(quest);
+                if is_new {
+                    state.quests.push(quest.clone());
+                }
+                is_new
+            })?;
+
+            if is_new {
+                GameLog::resource_push(world, format!("New quest: {}.", quest))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::get_inventory_list;
+
+    const TEST_DIALOGUE: &str = r#"
+        [dialogue.test]
+        start = "hello"
+
+        [[dialogue.test.node]]
+        id = "hello"
+        text = "Hello."
+
+        [[dialogue.test.node.choice]]
+        text = "Gift?"
+        next = "gift"
+        conditions = [{ not_flag = "gifted" }]
+        effects = [{ give_item = "Gem" }, { set_flag = "gifted" }]
+
+        [[dialogue.test.node.choice]]
+        text = "Show gem"
+        next = "hello"
+        conditions = [{ has_item = "Gem" }, { reaction = "friendly" }]
+        effects = [{ start_quest = "Find more gems" }]
+
+        [[dialogue.test.node.choice]]
+        text = "Bye"
+
+        [[dialogue.test.node]]
+        id = "gift"
+        text = "Here."
+
+        [[dialogue.test.node.choice]]
+        text = "Thanks"
+        next = "hello"
+    "#;
+
+    fn test_world() -> (World, Entity) {
+        let mut world = World::new();
+
+        world.spawn_resource(GameLog::new(), ()).unwrap();
+        world.spawn_resource(DialogueState::default(), ()).unwrap();
+        world
+            .spawn_resource(DialogueLibrary::from_str(TEST_DIALOGUE).unwrap(), ())
+            .unwrap();
+        world
+            .spawn_resource(Player, (Name("Player".into()), Faction::Player))
+            .unwrap();
+
+        let speaker = world.spawn((
+            Name("Villager".into()),
+            Faction::Townsfolk,
+            Speaker {
+                dialogue: "test".into(),
+            },
+        ));
+        world.spawn((Name("Gem".into()), InInventory { owner: speaker }));
+
+        (world, speaker)
+    }
+
+    fn choice_texts(world: &World, speaker: Entity, node: usize) -> Vec<String> {
+        available_choices(world, speaker, node)
+            .into_iter()
+            .map(|choice| choice.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_walk_dialogue_tree() {
+        let (mut world, speaker) = test_world();
+        let player = world.resource_entity::<Player>().unwrap();
+
+        let hello = match start_dialogue(&world, speaker) {
+            Some(RunState::Dialogue { node, .. }) => node,
+            other => panic!("Expected dialogue, got {:?}", other),
+        };
+        assert_eq!(node_text(&world, speaker, hello).unwrap(), "Hello.");
+        assert_eq!(choice_texts(&world, speaker, hello), vec!["Gift?", "Bye"]);
+
+        // Take the gift, which unlocks showing it and can only be taken once
+        let gift = match choose(&mut world, speaker, hello, 0).unwrap() {
+            RunState::Dialogue { node, .. } => node,
+            other => panic!("Expected dialogue, got {:?}", other),
+        };
+        assert_eq!(node_text(&world, speaker, gift).unwrap(), "Here.");
+        assert_eq!(get_inventory_list(&world, player).len(), 1);
+
+        assert_eq!(
+            choose(&mut world, speaker, gift, 0).unwrap(),
+            RunState::Dialogue {
+                speaker,
+                node: hello
+            }
+        );
+        assert_eq!(
+            choice_texts(&world, speaker, hello),
+            vec!["Show gem", "Bye"]
+        );
+
+        choose(&mut world, speaker, hello, 0).unwrap();
+        world
+            .resource::<DialogueState>()
+            .unwrap()
+            .map(|state| {
+                assert!(state.flags.contains("gifted"));
+                assert_eq!(state.quests, vec!["Find more gems".to_string()]);
+            })
+            .unwrap();
+
+        assert_eq!(
+            choose(&mut world, speaker, hello, 1).unwrap(),
+            RunState::AwaitingInput
+        );
+    }
+
+    #[test]
+    fn test_reaction_condition() {
+        let (mut world, speaker) = test_world();
+
+        choose(&mut world, speaker, 0, 0).unwrap();
+        *world.get_mut::<Faction>(speaker).unwrap() = Faction::Orcs;
+
+        assert_eq!(choice_texts(&world, speaker, 0), vec!["Bye"]);
+    }
+
+    #[test]
+    fn test_unknown_node_is_an_error() {
+        let s = r#"
+            [dialogue.test]
+            start = "hello"
+
+            [[dialogue.test.node]]
+            id = "hello"
+            text = "Hello."
+
+            [[dialogue.test.node.choice]]
+            text = "Go"
+            next = "nowhere"
+        "#;
+
+        assert!(DialogueLibrary::from_str(s).is_err());
+    }
+
+    #[test]
+    fn test_default_dialogue_loads() {
+        let library = DialogueLibrary::load_default().unwrap();
+        assert!(library.get("villager").is_some());
+    }
+}
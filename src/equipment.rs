@@ -0,0 +1,199 @@
+use crate::{
+    identification::{display_name, identify_item},
+    prelude::*,
+};
+
+/// What an entity's equipment adds to its `CombatStats`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EquipmentBonus {
+    pub power: i32,
+    pub defense: i32,
+}
+
+/// Sum the bonuses of everything `owner` has equipped, with each item's enchantment added to each
+/// of its bonuses
+pub fn equipment_bonus(world: &World, owner: Entity) -> EquipmentBonus {
+    let mut bonus = EquipmentBonus::default();
+
+    for (_, (equipped, power, defense, enchantment)) in world
+        .query::<(
+            &Equipped,
+            Option<&MeleePowerBonus>,
+            Option<&DefenseBonus>,
+            Option<&Enchantment>,
+        )>()
+        .into_iter()
+    {
+        if equipped.owner != owner {
+            continue;
+        }
+
+        let level = enchantment
+            .map(|enchantment| enchantment.level)
+            .unwrap_or(0);
+
+        if let Some(power) = power {
+            bonus.power += power.power + level;
+        }
+
+        if let Some(defense) = defense {
+            bonus.defense += defense.defense + level;
+        }
+    }
+
+    bonus
+}
+
+/// Unequip `item` if it is equipped, e.g. before it leaves its owner's inventory. Returns `false`
+/// if it can't be, because it is cursed.
+pub fn try_unequip(world: &mut World, item: Entity) -> anyhow::Result<bool> {
+    let owner = match world.get::<Equipped>(item) {
+        Ok(equipped) => equipped.owner,
+        Err(_) => return Ok(true),
+    };
+
+    let is_owner_player = world.get::<Player>(owner).is_ok();
+    let name = display_name(world, item).unwrap_or_default();
+
+    if world.get::<Cursed>(item).is_ok() {
+        if is_owner_player {
+            GameLog::resource_push(
+                world,
+                format!("You can't remove the {}, it is cursed!", name),
+            )?;
+        }
+
+        return Ok(false);
+    }
+
+    world.remove_one::<Equipped>(item)?;
+
+    if is_owner_player {
+        GameLog::resource_push(world, format!("You unequip the {}.", name))?;
+    }
+
+    Ok(true)
+}
+
+/// Equip `item`, replacing whatever `user` has equipped in the same slot, or unequip it if it is
+/// already equipped
+pub fn toggle_equipment(world: &mut World, user: Entity, item: Entity) -> anyhow::Result<()> {
+    if world.get::<Equipped>(item).is_ok() {
+        try_unequip(world, item)?;
+        return Ok(());
+    }
+
+    let slot = world.get::<Equippable>(item)?.slot;
+
+    let replaced = world
+        .query::<&Equipped>()
+        .into_iter()
+        .find(|(_, equipped)| equipped.owner == user && equipped.slot == slot)
+        .map(|(entity, _)| entity);
+
+    if let Some(replaced) = replaced {
+        if !try_unequip(world, replaced)? {
+            return Ok(());
+        }
+    }
+
+    world.insert_one(item, Equipped { owner: user, slot })?;
+
+    if world.get::<Player>(user).is_ok() {
+        // Equipping an item reveals its enchantment, and whether it is cursed
+        identify_item(world, item);
+
+        let name = display_name(world, item).unwrap_or_default();
+        GameLog::resource_push(world, format!("You equip the {}.", name))?;
+
+        if world.get::<Cursed>(item).is_ok() {
+            GameLog::resource_push(world, "It binds itself to you!")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Lift the curses from everything `owner` carries
+pub fn remove_curses(world: &mut World, owner: Entity) -> anyhow::Result<()> {
+    let cursed = world
+        .query::<(&InInventory, &Cursed)>()
+        .into_iter()
+        .filter(|(_, (in_inventory, _))| in_inventory.owner == owner)
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    let is_owner_player = world.get::<Player>(owner).is_ok();
+
+    if cursed.is_empty() && is_owner_player {
+        GameLog::resource_push(world, "You feel as if someone is watching over you.")?;
+    }
+
+    for item in cursed {
+        world.remove_one::<Cursed>(item)?;
+
+        if is_owner_player {
+            let name = display_name(world, item).unwrap_or_default();
+            GameLog::resource_push(world, format!("The {} glows softly.", name))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equipment_bonus() {
+        let mut world = World::new();
+        let owner = world.spawn((Name("Owner".into()),));
+
+        let sword = world.spawn((
+            Equippable {
+                slot: EquipmentSlot::Melee,
+            },
+            MeleePowerBonus { power: 2 },
+            Enchantment {
+                level: 1,
+                is_known: false,
+            },
+        ));
+        let shield = world.spawn((
+            Equippable {
+                slot: EquipmentSlot::Shield,
+            },
+            DefenseBonus { defense: 1 },
+            Enchantment {
+                level: -2,
+                is_known: false,
+            },
+            Cursed,
+        ));
+
+        toggle_equipment(&mut world, owner, sword).unwrap();
+        toggle_equipment(&mut world, owner, shield).unwrap();
+        assert_eq!(
+            equipment_bonus(&world, owner),
+            EquipmentBonus {
+                power: 3,
+                defense: -1,
+            }
+        );
+
+        // Cursed items stay on until the curse is lifted
+        assert!(!try_unequip(&mut world, shield).unwrap());
+        world.insert_one(shield, InInventory { owner }).unwrap();
+        remove_curses(&mut world, owner).unwrap();
+        assert!(try_unequip(&mut world, shield).unwrap());
+
+        assert_eq!(
+            equipment_bonus(&world, owner),
+            EquipmentBonus {
+                power: 3,
+                defense: 0,
+            }
+        );
+    }
+}
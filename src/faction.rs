@@ -0,0 +1,69 @@
+use serde::Deserialize;
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reaction {
+    Hostile,
+    Neutral,
+    Friendly,
+}
+
+/// How the first faction treats the second. Pairs which aren't listed are neutral, except members
+/// of the same faction, who are always friendly.
+const REACTIONS: &[(Faction, Faction, Reaction)] = &[
+    (Faction::Player, Faction::Townsfolk, Reaction::Friendly),
+    (Faction::Player, Faction::Orcs, Reaction::Hostile),
+    (Faction::Player, Faction::Goblins, Reaction::Hostile),
+    (Faction::Townsfolk, Faction::Player, Reaction::Friendly),
+    (Faction::Orcs, Faction::Player, Reaction::Hostile),
+    (Faction::Orcs, Faction::Townsfolk, Reaction::Hostile),
+    (Faction::Orcs, Faction::Goblins, Reaction::Hostile),
+    (Faction::Goblins, Faction::Player, Reaction::Hostile),
+    (Faction::Goblins, Faction::Orcs, Reaction::Hostile),
+];
+
+pub fn reaction(from: Faction, to: Faction) -> Reaction {
+    if from == to {
+        return Reaction::Friendly;
+    }
+
+    REACTIONS
+        .iter()
+        .find(|(a, b, _)| *a == from && *b == to)
+        .map(|(_, _, reaction)| *reaction)
+        .unwrap_or(Reaction::Neutral)
+}
+
+/// How entity `from` treats entity `to`. Entities without a `Faction` are neutral to everyone.
+pub fn entity_reaction(world: &World, from: Entity, to: Entity) -> Reaction {
+    match (world.get::<Faction>(from), world.get::<Faction>(to)) {
+        (Ok(from), Ok(to)) => reaction(*from, *to),
+        _ => Reaction::Neutral,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reaction() {
+        assert_eq!(reaction(Faction::Orcs, Faction::Orcs), Reaction::Friendly);
+        assert_eq!(reaction(Faction::Orcs, Faction::Player), Reaction::Hostile);
+        assert_eq!(reaction(Faction::Goblins, Faction::Orcs), Reaction::Hostile);
+        assert_eq!(
+            reaction(Faction::Player, Faction::Townsfolk),
+            Reaction::Friendly
+        );
+        assert_eq!(
+            reaction(Faction::Goblins, Faction::Townsfolk),
+            Reaction::Neutral
+        );
+        assert_eq!(
+            reaction(Faction::Townsfolk, Faction::Orcs),
+            Reaction::Neutral
+        );
+    }
+}
@@ -5,6 +5,7 @@ use crate::prelude::*;
 #[derive(Debug)]
 pub struct GameLog {
     pub entries: Vec<String>,
+    pub combat_message_count: usize,
 }
 
 impl GameLog {
@@ -14,13 +15,32 @@ impl GameLog {
         Ok(())
     }
 
+    /// Push a message about an attack or its outcome. These are counted so that automatic
+    /// movement can stop when combat happens.
+    pub fn resource_push_combat<S: Into<String>>(
+        world: &World,
+        msg: S,
+    ) -> Result<(), ComponentError> {
+        world
+            .resource::<GameLog>()?
+            .map(|mut gl| gl.push_combat(msg))?;
+
+        Ok(())
+    }
+
     pub fn new() -> Self {
         GameLog {
             entries: Vec::new(),
+            combat_message_count: 0,
         }
     }
 
     pub fn push<S: Into<String>>(&mut self, msg: S) {
         self.entries.push(msg.into());
     }
+
+    pub fn push_combat<S: Into<String>>(&mut self, msg: S) {
+        self.combat_message_count += 1;
+        self.push(msg);
+    }
 }
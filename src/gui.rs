@@ -1,57 +1,74 @@
-use crate::{inventory::get_inventory_list, prelude::*};
+use crate::{
+    auto_move::{known_travel_destinations, plan_travel_path},
+    dialogue::{available_choices, node_text},
+    identification::display_name,
+    inventory::{carried_weight, get_inventory_list},
+    look::describe_tile,
+    morgue::RunStats,
+    prelude::*,
+    projectile::flight_path,
+    scores::{score_table_header, score_table_rows, ScoreEntry, ScoreSort},
+    shop::{shop_listings, vendor_at, ShopMode},
+    throwing::throw_target_error,
+    GAME_TITLE,
+};
 
 pub const MAP_VIEW_WIDTH: usize = 80;
 pub const MAP_VIEW_HEIGHT: usize = 43;
 
 const TOOLTIP_HORIZONTAL_PADDING: i32 = 1;
 
+/// Options on each page of a paged menu, one for each letter
+const MENU_PAGE_SIZE: usize = 26;
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum ItemMenuResult {
     Cancel,
     NoResponse,
-    Selected,
+    /// Index of the selected option
+    Selected(usize),
+    /// Show this page of a paged menu
+    Page(usize),
 }
 
-pub fn ui_inventory_use_input(context: &mut BTerm, world: &mut World) -> ItemMenuResult {
-    let (action, selected) = ui_inventory_menu_input(context, world);
-
-    if let Some((user, item)) = selected {
-        world.spawn_command(UseItemCommand { user, item });
-    }
-
-    action
+/// Number of pages needed to show `len` options, at least one even if there are none
+fn menu_page_count(len: usize) -> usize {
+    (len.max(1) - 1) / MENU_PAGE_SIZE + 1
 }
 
-pub fn ui_inventory_drop_input(context: &mut BTerm, world: &mut World) -> ItemMenuResult {
-    let (action, selected) = ui_inventory_menu_input(context, world);
-
-    if let Some((dropper, item)) = selected {
-        world.spawn_command(DropItemCommand { dropper, item });
-    }
-
-    action
-}
+/// Select an item from the player's inventory, on `page` of the menu
+pub fn ui_inventory_menu_input(context: &mut BTerm, world: &World, page: usize) -> ItemMenuResult {
+    let inventory_len = match world.resource_entity::<Player>() {
+        Ok(player) => get_inventory_list(world, player).len(),
+        Err(_) => return ItemMenuResult::NoResponse,
+    };
 
-fn ui_inventory_menu_input(
-    context: &mut BTerm,
-    world: &mut World,
-) -> (ItemMenuResult, Option<(Entity, Entity)>) {
     match context.key {
-        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, None),
+        Some(VirtualKeyCode::Escape) => ItemMenuResult::Cancel,
+        Some(VirtualKeyCode::PageDown) | Some(VirtualKeyCode::Right)
+            if page + 1 < menu_page_count(inventory_len) =>
+        {
+            ItemMenuResult::Page(page + 1)
+        }
+        Some(VirtualKeyCode::PageUp) | Some(VirtualKeyCode::Left) if page > 0 => {
+            ItemMenuResult::Page(page - 1)
+        }
         Some(key) => {
-            if let Ok(player) = world.resource_entity::<Player>() {
-                let inventory = get_inventory_list(world, player);
-                let selection = letter_to_option(key);
-                if let Some((item, _)) = inventory.get(selection as usize) {
-                    (ItemMenuResult::Selected, Some((player, *item)))
-                } else {
-                    (ItemMenuResult::NoResponse, None)
-                }
+            let selection = letter_to_option(key);
+
+            if selection < 0 {
+                return ItemMenuResult::NoResponse;
+            }
+
+            let index = page * MENU_PAGE_SIZE + selection as usize;
+
+            if index < inventory_len {
+                ItemMenuResult::Selected(index)
             } else {
-                (ItemMenuResult::NoResponse, None)
+                ItemMenuResult::NoResponse
             }
         }
-        None => (ItemMenuResult::NoResponse, None),
+        None => ItemMenuResult::NoResponse,
     }
 }
 
@@ -100,6 +117,29 @@ pub fn draw_ui(context: &mut BTerm, world: &World, config: &Config) {
         );
     }
 
+    if let Some((_, (_, gold))) = world.query::<(&Player, &Gold)>().into_iter().next() {
+        let gold = format!(" Gold: {} ", gold.amount);
+        context.print_color(2, 49, config.ui_title.fg, config.ui_title.bg, &gold);
+    }
+
+    if let Some((player, (_, capacity, encumbered))) = world
+        .query::<(&Player, &InventoryCapacity, Option<&Encumbered>)>()
+        .into_iter()
+        .next()
+    {
+        let load = format!(
+            " Load: {:.0} / {:.0} lbs{} ",
+            carried_weight(world, player),
+            capacity.max_weight,
+            if encumbered.is_some() {
+                " (Encumbered)"
+            } else {
+                ""
+            }
+        );
+        context.print_color(16, 49, config.ui_title.fg, config.ui_title.bg, &load);
+    }
+
     if let Some((_, log)) = world.query::<&GameLog>().into_iter().next() {
         for (i, msg) in log.entries.iter().rev().enumerate() {
             let y = 44 + i;
@@ -113,9 +153,31 @@ pub fn draw_ui(context: &mut BTerm, world: &World, config: &Config) {
     // let mouse_pos = context.mouse_pos();
     // context.set_bg(mouse_pos.0, mouse_pos.1, config.ui_tooltip.bg);
 
+    draw_travel_preview(context, world, config);
     draw_tooltips(context, world, config);
 }
 
+/// Highlight the path the player would take when clicking on the tile under the mouse
+fn draw_travel_preview(context: &mut BTerm, world: &World, config: &Config) {
+    if world.resource_clone::<RunState>().ok() != Some(RunState::AwaitingInput) {
+        return;
+    }
+
+    if let Ok(player) = world.resource_entity::<Player>() {
+        if world.get::<AutoMove>(player).is_ok() {
+            return;
+        }
+    }
+
+    let (mx, my) = context.mouse_pos();
+
+    if let Some(path) = plan_travel_path(world, mx, my) {
+        for point in path {
+            context.set_bg(point.x, point.y, config.ui_travel_path.bg);
+        }
+    }
+}
+
 fn draw_tooltips(context: &mut BTerm, world: &World, config: &Config) {
     let (mx, my) = context.mouse_pos();
 
@@ -127,11 +189,7 @@ fn draw_tooltips(context: &mut BTerm, world: &World, config: &Config) {
         let tooltip = map
             .get_entities_on_tile(mx, my)
             .iter()
-            .filter_map(|entity| {
-                let mut query = world.query_one::<&Name>(*entity).ok()?;
-                let Name(name) = query.get().expect("Unfiltered query");
-                Some(name.clone())
-            })
+            .filter_map(|entity| display_name(world, *entity))
             .collect::<Vec<_>>();
 
         let tooltip_width = 2 * TOOLTIP_HORIZONTAL_PADDING
@@ -261,24 +319,48 @@ fn draw_select_menu<S: AsRef<str>>(
     }
 }
 
-pub fn draw_inventory_menu(context: &mut BTerm, world: &World, config: &Config, title: &str) {
+fn menu_box_style(config: &Config) -> MenuBoxStyle {
+    MenuBoxStyle {
+        pad: 2,
+        fg: config.ui.fg,
+        bg: config.ui.bg,
+        highlight_fg: config.ui_title.fg,
+        highlight_bg: config.ui_title.bg,
+    }
+}
+
+pub fn draw_inventory_menu(
+    context: &mut BTerm,
+    world: &World,
+    config: &Config,
+    title: &str,
+    page: usize,
+) {
     if let Ok(player) = world.resource_entity::<Player>() {
-        let menu_options = get_inventory_list(world, player)
+        let inventory = get_inventory_list(world, player);
+        let page_count = menu_page_count(inventory.len());
+
+        let menu_options = inventory
             .into_iter()
+            .skip(page * MENU_PAGE_SIZE)
+            .take(MENU_PAGE_SIZE)
             .map(|(_, name)| name)
             .collect::<Vec<_>>();
 
+        let (title, footer) = if page_count > 1 {
+            (
+                format!("{} ({}/{})", title, page + 1, page_count),
+                "PGUP/PGDN for more, ESCAPE to cancel",
+            )
+        } else {
+            (title.to_string(), "ESCAPE to cancel")
+        };
+
         draw_select_menu(
             context,
-            &MenuBoxStyle {
-                pad: 2,
-                fg: config.ui.fg,
-                bg: config.ui.bg,
-                highlight_fg: config.ui_title.fg,
-                highlight_bg: config.ui_title.bg,
-            },
-            title,
-            "ESCAPE to cancel",
+            &menu_box_style(config),
+            &title,
+            footer,
             15,
             25 - menu_options.len() as i32 / 2,
             &menu_options,
@@ -286,10 +368,525 @@ pub fn draw_inventory_menu(context: &mut BTerm, world: &World, config: &Config,
     }
 }
 
+/// Select one of the `shop_listings` of the vendor at `(x, y)`
+pub fn ui_shop_menu_input(
+    context: &mut BTerm,
+    world: &World,
+    x: i32,
+    y: i32,
+    mode: ShopMode,
+) -> ItemMenuResult {
+    let vendor = match vendor_at(world, x, y) {
+        Some(vendor) => vendor,
+        None => return ItemMenuResult::Cancel,
+    };
+
+    match context.key {
+        Some(VirtualKeyCode::Escape) => ItemMenuResult::Cancel,
+        Some(key) => {
+            let selection = letter_to_option(key);
+
+            if selection >= 0 && (selection as usize) < shop_listings(world, vendor, mode).len() {
+                ItemMenuResult::Selected(selection as usize)
+            } else {
+                ItemMenuResult::NoResponse
+            }
+        }
+        None => ItemMenuResult::NoResponse,
+    }
+}
+
+pub fn draw_shop_menu(
+    context: &mut BTerm,
+    world: &World,
+    config: &Config,
+    x: i32,
+    y: i32,
+    mode: ShopMode,
+) {
+    let vendor = match vendor_at(world, x, y) {
+        Some(vendor) => vendor,
+        None => return,
+    };
+
+    let vendor_name = match world.get::<Name>(vendor) {
+        Ok(name) => name.to_string(),
+        Err(_) => return,
+    };
+
+    let gold = match world.resource_entity::<Player>() {
+        Ok(player) => world
+            .get::<Gold>(player)
+            .map(|gold| gold.amount)
+            .unwrap_or(0),
+        Err(_) => 0,
+    };
+
+    let (title, footer) = match mode {
+        ShopMode::Buy => (
+            format!("Buy from {} ({} gold)", vendor_name, gold),
+            "TAB to sell, ESCAPE to leave",
+        ),
+        ShopMode::Sell => (
+            format!("Sell to {} ({} gold)", vendor_name, gold),
+            "TAB to buy, ESCAPE to leave",
+        ),
+    };
+
+    let menu_options = shop_listings(world, vendor, mode)
+        .into_iter()
+        .map(|listing| match listing.price {
+            Some(price) => format!("{} - {} gold", listing.name, price),
+            None => format!("{} - not wanted", listing.name),
+        })
+        .collect::<Vec<_>>();
+
+    draw_select_menu(
+        context,
+        &menu_box_style(config),
+        &title,
+        footer,
+        15,
+        25 - menu_options.len() as i32 / 2,
+        &menu_options,
+    );
+}
+
+/// Pick one of the `available_choices` of a dialogue node
+pub fn ui_dialogue_input(
+    context: &mut BTerm,
+    world: &World,
+    speaker: Entity,
+    node: usize,
+) -> ItemMenuResult {
+    match context.key {
+        Some(VirtualKeyCode::Escape) => ItemMenuResult::Cancel,
+        Some(key) => {
+            let selection = letter_to_option(key);
+
+            if selection >= 0
+                && (selection as usize) < available_choices(world, speaker, node).len()
+            {
+                ItemMenuResult::Selected(selection as usize)
+            } else {
+                ItemMenuResult::NoResponse
+            }
+        }
+        None => ItemMenuResult::NoResponse,
+    }
+}
+
+/// Split `text` into lines of at most `width` characters, breaking between words
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+pub fn draw_dialogue(
+    context: &mut BTerm,
+    world: &World,
+    config: &Config,
+    speaker: Entity,
+    node: usize,
+) {
+    const TEXT_WIDTH: usize = 50;
+
+    let style = menu_box_style(config);
+
+    let speaker_name = match world.get::<Name>(speaker) {
+        Ok(name) => name.to_string(),
+        Err(_) => return,
+    };
+
+    let text_lines = wrap_text(
+        &node_text(world, speaker, node).unwrap_or_default(),
+        TEXT_WIDTH,
+    );
+    let choices = available_choices(world, speaker, node);
+
+    let width = TEXT_WIDTH as i32 + style.pad * 2 - 1;
+    let height = (text_lines.len() + 1 + choices.len()) as i32 + style.pad * 2 - 1;
+    let box_x = (MAP_VIEW_WIDTH as i32 - width) / 2;
+    let box_y = 8;
+
+    draw_menu_box(
+        context,
+        &style,
+        &speaker_name,
+        "ESCAPE to leave",
+        box_x,
+        box_y,
+        width,
+        height,
+    );
+
+    let inner_x = box_x + style.pad;
+    let inner_y = box_y + style.pad;
+
+    for (i, line) in text_lines.iter().enumerate() {
+        context.print(inner_x, inner_y + i as i32, line);
+    }
+
+    let choices_y = inner_y + text_lines.len() as i32 + 1;
+
+    for (i, choice) in choices.iter().enumerate() {
+        let item_y = choices_y + i as i32;
+
+        context.set(inner_x, item_y, style.fg, style.bg, to_cp437('('));
+        context.set(
+            inner_x + 1,
+            item_y,
+            style.highlight_fg,
+            style.highlight_bg,
+            index_to_letter(i),
+        );
+        context.set(inner_x + 2, item_y, style.fg, style.bg, to_cp437(')'));
+
+        context.print(inner_x + 4, item_y, &choice.text);
+    }
+}
+
+/// Select one of the `known_travel_destinations`
+pub fn ui_travel_menu_input(context: &mut BTerm, world: &World) -> ItemMenuResult {
+    match context.key {
+        Some(VirtualKeyCode::Escape) => ItemMenuResult::Cancel,
+        Some(key) => {
+            let destinations = known_travel_destinations(world);
+            let selection = letter_to_option(key);
+
+            if selection >= 0 && (selection as usize) < destinations.len() {
+                ItemMenuResult::Selected(selection as usize)
+            } else {
+                ItemMenuResult::NoResponse
+            }
+        }
+        None => ItemMenuResult::NoResponse,
+    }
+}
+
+pub fn draw_travel_menu(context: &mut BTerm, world: &World, config: &Config) {
+    let menu_options = known_travel_destinations(world)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+
+    draw_select_menu(
+        context,
+        &menu_box_style(config),
+        "Travel to",
+        "ESCAPE to cancel",
+        15,
+        25 - menu_options.len() as i32 / 2,
+        &menu_options,
+    );
+}
+
+/// Highlight the tile under the look cursor and describe it in a panel on the other side of
+/// the screen
+pub fn draw_look(context: &mut BTerm, world: &World, config: &Config, x: i32, y: i32) {
+    context.set_bg(x, y, config.ui_look_cursor.bg);
+
+    const FOOTER: &str = "ESCAPE to exit";
+
+    let style = menu_box_style(config);
+    let lines = describe_tile(world, x, y);
+
+    let inner_width = lines
+        .iter()
+        .map(|s| s.len() as i32)
+        .chain(std::iter::once(FOOTER.len() as i32))
+        .max()
+        .unwrap_or(0);
+    let inner_height = lines.len() as i32;
+    let width = inner_width + style.pad * 2 - 1;
+
+    let box_x = if x < MAP_VIEW_WIDTH as i32 / 2 {
+        MAP_VIEW_WIDTH as i32 - width - 2
+    } else {
+        1
+    };
+    let box_y = 1;
+
+    draw_menu_box(
+        context,
+        &style,
+        "Look",
+        FOOTER,
+        box_x,
+        box_y,
+        width,
+        inner_height + style.pad * 2 - 1,
+    );
+
+    for (i, line) in lines.iter().enumerate() {
+        context.print(box_x + style.pad, box_y + style.pad + i as i32, line);
+    }
+}
+
+/// Show where the item at `index` in the player's inventory list would fly if thrown at `(x, y)`
+pub fn draw_throw_target(
+    context: &mut BTerm,
+    world: &World,
+    config: &Config,
+    index: usize,
+    x: i32,
+    y: i32,
+) {
+    const FOOTER: &str = "ENTER to throw, ESCAPE to cancel";
+
+    let player = match world.resource_entity::<Player>() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    if let (Ok(pos), Some((_, map))) = (
+        world.get::<Position>(player),
+        world.query::<&TileMap>().into_iter().next(),
+    ) {
+        for point in flight_path(pos.to_point(), Point::new(x, y), |p| {
+            !map.is_tile_walkable(p.x, p.y)
+        }) {
+            context.set_bg(point.x, point.y, config.ui_target_path.bg);
+        }
+    }
+
+    context.set_bg(x, y, config.ui_look_cursor.bg);
+
+    let item_name = match get_inventory_list(world, player).into_iter().nth(index) {
+        Some((_, name)) => name,
+        None => return,
+    };
+
+    let style = menu_box_style(config);
+    let lines = [
+        format!("The {}", item_name),
+        throw_target_error(world, Position { x, y })
+            .unwrap_or("")
+            .to_string(),
+    ];
+
+    let inner_width = lines
+        .iter()
+        .map(|s| s.len() as i32)
+        .chain(std::iter::once(FOOTER.len() as i32))
+        .max()
+        .unwrap_or(0);
+    let inner_height = lines.len() as i32;
+    let width = inner_width + style.pad * 2 - 1;
+
+    let box_x = if x < MAP_VIEW_WIDTH as i32 / 2 {
+        MAP_VIEW_WIDTH as i32 - width - 2
+    } else {
+        1
+    };
+    let box_y = 1;
+
+    draw_menu_box(
+        context,
+        &style,
+        "Throw",
+        FOOTER,
+        box_x,
+        box_y,
+        width,
+        inner_height + style.pad * 2 - 1,
+    );
+
+    for (i, line) in lines.iter().enumerate() {
+        context.print(box_x + style.pad, box_y + style.pad + i as i32, line);
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum MainMenuOption {
+    NewGame,
+    HighScores,
+    Quit,
+}
+
+const MAIN_MENU_OPTIONS: [(MainMenuOption, &str); 3] = [
+    (MainMenuOption::NewGame, "New game"),
+    (MainMenuOption::HighScores, "High scores"),
+    (MainMenuOption::Quit, "Quit"),
+];
+
+/// Most runs shown in the high score table
+const MAX_SCORE_ROWS: usize = 30;
+
+pub fn ui_main_menu_input(context: &mut BTerm) -> Option<MainMenuOption> {
+    match context.key {
+        Some(VirtualKeyCode::Escape) => Some(MainMenuOption::Quit),
+        Some(key) => {
+            let selection = letter_to_option(key);
+
+            if selection >= 0 {
+                MAIN_MENU_OPTIONS
+                    .get(selection as usize)
+                    .map(|(option, _)| *option)
+            } else {
+                None
+            }
+        }
+        None => None,
+    }
+}
+
+pub fn draw_main_menu(context: &mut BTerm, config: &Config) {
+    let menu_options = MAIN_MENU_OPTIONS
+        .iter()
+        .map(|(_, label)| *label)
+        .collect::<Vec<_>>();
+
+    draw_select_menu(
+        context,
+        &menu_box_style(config),
+        GAME_TITLE,
+        "ESCAPE to quit",
+        25,
+        20,
+        &menu_options,
+    );
+}
+
+/// Change the sort order of the high score table, or go back to the main menu
+pub fn ui_scores_input(context: &mut BTerm, sort: ScoreSort) -> RunState {
+    let sort = match context.key {
+        Some(VirtualKeyCode::Escape) => return RunState::MainMenu,
+        Some(VirtualKeyCode::D) => ScoreSort::Depth,
+        Some(VirtualKeyCode::X) => ScoreSort::Xp,
+        Some(VirtualKeyCode::T) => ScoreSort::Turns,
+        Some(VirtualKeyCode::R) => ScoreSort::Recent,
+        _ => sort,
+    };
+
+    RunState::ShowScores { sort }
+}
+
+/// `scores` must already be sorted by `sort`
+pub fn draw_scores(context: &mut BTerm, config: &Config, scores: &[ScoreEntry], sort: ScoreSort) {
+    const FOOTER: &str = "Sort by (D)epth (X)P (T)urns (R)ecent, ESCAPE to return";
+
+    let style = menu_box_style(config);
+    let title = format!("High Scores by {}", sort.label());
+    let header = score_table_header();
+
+    let mut rows = score_table_rows(&scores[..scores.len().min(MAX_SCORE_ROWS)]);
+    if rows.is_empty() {
+        rows.push("No scores yet.".to_string());
+    }
+
+    let inner_width = rows
+        .iter()
+        .map(|s| s.len() as i32)
+        .chain(std::iter::once(header.len() as i32))
+        .chain(std::iter::once(FOOTER.len() as i32))
+        .max()
+        .unwrap_or(0);
+    let width = inner_width + style.pad * 2 - 1;
+    let height = rows.len() as i32 + 1 + style.pad * 2 - 1;
+
+    let box_x = (MAP_VIEW_WIDTH as i32 - width) / 2;
+    let box_y = 2;
+
+    draw_menu_box(context, &style, &title, FOOTER, box_x, box_y, width, height);
+
+    let inner_x = box_x + style.pad;
+    let inner_y = box_y + style.pad;
+
+    context.print_color(
+        inner_x,
+        inner_y,
+        style.highlight_fg,
+        style.highlight_bg,
+        &header,
+    );
+
+    for (i, row) in rows.iter().enumerate() {
+        context.print(inner_x, inner_y + 1 + i as i32, row);
+    }
+}
+
+/// Shown after the player dies
+pub fn draw_game_over(context: &mut BTerm, world: &World, config: &Config) {
+    const FOOTER: &str = "ESCAPE to quit";
+
+    let style = menu_box_style(config);
+
+    let cause_of_death = world
+        .query::<&RunStats>()
+        .into_iter()
+        .next()
+        .and_then(|(_, run_stats)| run_stats.cause_of_death.clone())
+        .unwrap_or_else(|| "You died".to_string());
+
+    let lines = [format!("{}.", cause_of_death)];
+
+    let inner_width = lines
+        .iter()
+        .map(|s| s.len() as i32)
+        .chain(std::iter::once(FOOTER.len() as i32))
+        .max()
+        .unwrap_or(0);
+    let width = inner_width + style.pad * 2 - 1;
+    let height = lines.len() as i32 + style.pad * 2 - 1;
+
+    let box_x = (MAP_VIEW_WIDTH as i32 - width) / 2;
+    let box_y = (MAP_VIEW_HEIGHT as i32 - height) / 2;
+
+    draw_menu_box(
+        context,
+        &style,
+        "Game Over",
+        FOOTER,
+        box_x,
+        box_y,
+        width,
+        height,
+    );
+
+    for (i, line) in lines.iter().enumerate() {
+        context.print(box_x + style.pad, box_y + style.pad + i as i32, line);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_wrap_text() {
+        assert_eq!(
+            wrap_text("the quick brown fox jumps", 10),
+            vec!["the quick", "brown fox", "jumps"]
+        );
+        assert_eq!(wrap_text("", 10), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_menu_page_count() {
+        assert_eq!(menu_page_count(0), 1);
+        assert_eq!(menu_page_count(26), 1);
+        assert_eq!(menu_page_count(27), 2);
+        assert_eq!(menu_page_count(53), 3);
+    }
+
     #[test]
     fn test_index_to_letter() {
         assert_eq!(index_to_letter(0), 'a' as FontCharType);
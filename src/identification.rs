@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::prelude::*;
+
+/// Kinds of items, by their real names, which look alike until the player identifies them
+const POTION_KINDS: &[&str] = &["Health Potion", "Potion of Telepathy"];
+const SCROLL_KINDS: &[&str] = &[
+    "Scroll of Magic Mapping",
+    "Scroll of Identify",
+    "Scroll of Remove Curse",
+];
+
+const POTION_APPEARANCES: &[&str] = &[
+    "Murky Potion",
+    "Bubbling Potion",
+    "Violet Potion",
+    "Smoky Potion",
+    "Golden Potion",
+    "Fizzy Potion",
+    "Oily Potion",
+    "Pink Potion",
+];
+
+/// Scroll labels are made of two of these, e.g. "XYZZY"
+const SCROLL_SYLLABLES: &[&str] = &[
+    "XY", "ZZY", "FOO", "BAR", "KLAA", "TU", "NOK", "ELB", "VE", "RUM", "ZOK", "PHA",
+];
+
+/// What the player knows about items. Each kind of item which needs identifying gets a random
+/// appearance every run, which is shown instead of its name until it is identified.
+#[derive(Debug, Default)]
+pub struct Identification {
+    /// Appearances of the kinds of items which need identifying, by their real names
+    appearances: BTreeMap<String, String>,
+    known: BTreeSet<String>,
+}
+
+impl Identification {
+    pub fn new(rng: &mut RandomNumberGenerator) -> Self {
+        let mut appearances = BTreeMap::new();
+
+        let mut potion_appearances = POTION_APPEARANCES.to_vec();
+        for kind in POTION_KINDS {
+            let i = rng.range(0, potion_appearances.len() as i32) as usize;
+            appearances.insert(kind.to_string(), potion_appearances.remove(i).to_string());
+        }
+
+        for kind in SCROLL_KINDS {
+            let appearance = loop {
+                let label = (0..2)
+                    .map(|_| SCROLL_SYLLABLES[rng.range(0, SCROLL_SYLLABLES.len() as i32) as usize])
+                    .collect::<String>();
+                let appearance = format!("Scroll labeled {}", label);
+
+                if !appearances.values().any(|other| *other == appearance) {
+                    break appearance;
+                }
+            };
+
+            appearances.insert(kind.to_string(), appearance);
+        }
+
+        Self {
+            appearances,
+            known: BTreeSet::new(),
+        }
+    }
+
+    pub fn is_known(&self, name: &str) -> bool {
+        !self.appearances.contains_key(name) || self.known.contains(name)
+    }
+
+    /// What the player sees an item called `name` as
+    pub fn known_name<'a>(&'a self, name: &'a str) -> &'a str {
+        match self.appearances.get(name) {
+            Some(appearance) if !self.known.contains(name) => appearance,
+            _ => name,
+        }
+    }
+
+    /// Identify the kind of item called `name`, returning whether it wasn't known before
+    pub fn identify(&mut self, name: &str) -> bool {
+        !self.is_known(name) && self.known.insert(name.to_string())
+    }
+}
+
+/// What the player sees an item called `name` as, e.g. "Murky Potion" for an unidentified Health
+/// Potion
+pub fn known_name(world: &World, name: &str) -> String {
+    match world.query::<&Identification>().into_iter().next() {
+        Some((_, identification)) => identification.known_name(name).to_string(),
+        None => name.to_string(),
+    }
+}
+
+/// The `Name` of an entity as the player knows it, with its enchantment once that is known, e.g.
+/// "-1 Dagger (cursed)"
+pub fn display_name(world: &World, entity: Entity) -> Option<String> {
+    let name = world.get::<Name>(entity).ok()?;
+    let name = known_name(world, name.as_str());
+
+    Some(match world.get::<Enchantment>(entity) {
+        Ok(enchantment) if enchantment.is_known => {
+            let cursed = if world.get::<Cursed>(entity).is_ok() {
+                " (cursed)"
+            } else {
+                ""
+            };
+            format!("{:+} {}{}", enchantment.level, name, cursed)
+        }
+        _ => name,
+    })
+}
+
+/// Identify the kind of `item` and its enchantment, returning whether either wasn't known before
+pub fn identify_item(world: &World, item: Entity) -> bool {
+    let name = match world.get::<Name>(item) {
+        Ok(name) => name,
+        Err(_) => return false,
+    };
+
+    let is_enchantment_new = match world.get_mut::<Enchantment>(item) {
+        Ok(mut enchantment) if !enchantment.is_known => {
+            enchantment.is_known = true;
+            true
+        }
+        _ => false,
+    };
+
+    let is_kind_new = match world.query::<&mut Identification>().into_iter().next() {
+        Some((_, identification)) => identification.identify(name.as_str()),
+        None => false,
+    };
+
+    is_kind_new || is_enchantment_new
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_appearances_are_unique_and_seeded() {
+        let identification = Identification::new(&mut RandomNumberGenerator::seeded(7));
+        let same_seed = Identification::new(&mut RandomNumberGenerator::seeded(7));
+
+        let appearances = identification.appearances.values().collect::<BTreeSet<_>>();
+        assert_eq!(appearances.len(), POTION_KINDS.len() + SCROLL_KINDS.len());
+        assert_eq!(identification.appearances, same_seed.appearances);
+    }
+
+    #[test]
+    fn test_identify() {
+        let mut identification = Identification::new(&mut RandomNumberGenerator::seeded(7));
+
+        assert_ne!(identification.known_name("Health Potion"), "Health Potion");
+        assert_eq!(identification.known_name("Dagger"), "Dagger");
+
+        assert!(identification.identify("Health Potion"));
+        assert!(!identification.identify("Health Potion"));
+        assert!(!identification.identify("Dagger"));
+        assert_eq!(identification.known_name("Health Potion"), "Health Potion");
+    }
+}
@@ -1,4 +1,15 @@
-use crate::{despawn_entities_system::queue_despawn_batch, prelude::*};
+use crate::{
+    despawn_entities_system::queue_despawn_batch,
+    equipment::{remove_curses, toggle_equipment, try_unequip},
+    identification::{display_name, identify_item},
+    map_indexing_system::unindex_entity,
+    particle::spawn_number,
+    prelude::*,
+    spawner,
+};
+
+/// No one can carry more than this many times the weight which encumbers them
+const MAX_LOAD_FACTOR: f32 = 2.0;
 
 pub fn pickup_item_system(world: &mut World) -> anyhow::Result<()> {
     let player = world.resource_entity::<Player>().ok();
@@ -9,25 +20,54 @@ pub fn pickup_item_system(world: &mut World) -> anyhow::Result<()> {
         .map(|(_, cmd)| *cmd)
         .collect::<Vec<_>>();
 
+    let mut gold_piles_to_despawn = Vec::new();
+
     for pickup_item_command in pickup_commands {
-        if let Err(err) = world.remove_one::<Position>(pickup_item_command.item) {
-            console::log(format!(
-                "Error: Failed to remove Position from item {}: {}",
-                pickup_item_command.item.id(),
-                err
-            ));
+        // Gold goes into the collector's purse instead of their inventory
+        if let Ok(pile) = world
+            .get::<Gold>(pickup_item_command.item)
+            .map(|gold| *gold)
+        {
+            let purse = world
+                .get::<Gold>(pickup_item_command.collector)
+                .map(|gold| gold.amount)
+                .unwrap_or(0);
+
+            world.insert_one(
+                pickup_item_command.collector,
+                Gold {
+                    amount: purse + pile.amount,
+                },
+            )?;
+            gold_piles_to_despawn.push(pickup_item_command.item);
+
+            if Some(pickup_item_command.collector) == player {
+                GameLog::resource_push(world, format!("You pick up {} gold.", pile.amount))?;
+            }
 
             continue;
         }
 
-        if let Err(err) = world.insert_one(
+        if let Err(err) = check_capacity(
+            world,
+            pickup_item_command.collector,
             pickup_item_command.item,
-            InInventory {
-                owner: pickup_item_command.collector,
-            },
+        ) {
+            if Some(pickup_item_command.collector) == player {
+                let name = display_name(world, pickup_item_command.item).unwrap_or_default();
+                GameLog::resource_push(world, err.message(&name))?;
+            }
+
+            continue;
+        }
+
+        if let Err(err) = add_to_inventory(
+            world,
+            pickup_item_command.item,
+            pickup_item_command.collector,
         ) {
             console::log(format!(
-                "Error: Failed to add InInventory to item {}: {}",
+                "Error: Failed to add item {} to inventory: {}",
                 pickup_item_command.item.id(),
                 err
             ));
@@ -36,31 +76,33 @@ pub fn pickup_item_system(world: &mut World) -> anyhow::Result<()> {
         }
 
         if Some(pickup_item_command.collector) == player {
-            match world.get::<Name>(pickup_item_command.item) {
-                Ok(item_name) => {
-                    GameLog::resource_push(
-                        world,
-                        format!("You pick up the {}.", item_name.as_str()),
-                    )?;
+            match display_name(world, pickup_item_command.item) {
+                Some(item_name) => {
+                    GameLog::resource_push(world, format!("You pick up the {}.", item_name))?;
                 }
-                Err(err) => {
+                None => {
                     console::log(format!(
-                        "Error: Failed to log pickup message for item entity {}: {}",
-                        pickup_item_command.item.id(),
-                        err
+                        "Error: Failed to log pickup message for item entity {}",
+                        pickup_item_command.item.id()
                     ));
                 }
             }
         }
     }
 
+    queue_despawn_batch(world, gold_piles_to_despawn);
+
     Ok(())
 }
 
-pub fn use_item_system(world: &mut World) -> anyhow::Result<()> {
+pub fn use_item_system(world: &mut World, config: &Config) -> anyhow::Result<()> {
     let player = world.resource_entity::<Player>().ok();
 
     let mut items_to_despawn = Vec::new();
+    let mut healed = Vec::new();
+    let mut telepathy_to_grant = Vec::new();
+    let mut equipment_to_toggle = Vec::new();
+    let mut curses_to_remove = Vec::new();
 
     for (_, UseItemCommand { user, item }) in world.query::<&UseItemCommand>().into_iter() {
         let user = *user;
@@ -68,27 +110,31 @@ pub fn use_item_system(world: &mut World) -> anyhow::Result<()> {
 
         let is_user_player = Some(user) == player;
 
-        let mut item_name = match world.query_one::<&Name>(item) {
-            Ok(query) => query,
-            Err(err) => {
-                console::log(format!(
-                    "Error: Failed to get item {} name: {}",
-                    item.id(),
-                    err
-                ));
+        // Using equipment puts it on or takes it off
+        if world.get::<Equippable>(item).is_ok() {
+            equipment_to_toggle.push((user, item));
+            continue;
+        }
+
+        let item_name = match display_name(world, item) {
+            Some(name) => name,
+            None => {
+                console::log(format!("Error: Failed to get item {} name", item.id()));
                 continue;
             }
         };
 
-        let Name(item_name) = item_name.get().expect("Unfiltered query");
-
         if is_user_player {
             GameLog::resource_push(world, format!("You use the {}.", item_name))?;
+        } else if is_visible_to_player(world, user) {
+            let user_name = display_name(world, user).unwrap_or_default();
+            GameLog::resource_push(world, format!("The {} uses a {}.", user_name, item_name))?;
         }
 
         if let Ok(mut stats) = world.get_mut::<CombatStats>(user) {
             if let Ok(healing_item) = world.get::<HealingItem>(item) {
                 stats.hp = i32::min(stats.max_hp, stats.hp + healing_item.heal_amount);
+                healed.push((user, healing_item.heal_amount));
 
                 if is_user_player {
                     GameLog::resource_push(
@@ -99,7 +145,82 @@ pub fn use_item_system(world: &mut World) -> anyhow::Result<()> {
             }
         }
 
-        items_to_despawn.push(item);
+        if let Ok(telepathy_item) = world.get::<TelepathyItem>(item) {
+            telepathy_to_grant.push((user, telepathy_item.duration));
+
+            if is_user_player {
+                GameLog::resource_push(world, "You sense the minds around you.")?;
+            }
+        }
+
+        if world.get::<MagicMappingItem>(item).is_ok() {
+            world
+                .resource::<TileMap>()?
+                .map(|mut map| map.reveal_all_tiles())?;
+
+            if is_user_player {
+                GameLog::resource_push(world, "The layout of the level floods into your mind.")?;
+            }
+        }
+
+        if world.get::<DetectsMonsters>(item).is_ok() && is_user_player {
+            GameLog::resource_push(world, "It works as long as you carry it.")?;
+        }
+
+        if world.get::<IdentifyItem>(item).is_ok() && is_user_player {
+            for (carried, _) in get_inventory_list(world, user) {
+                let old_name = display_name(world, carried).unwrap_or_default();
+
+                if identify_item(world, carried) {
+                    let name = display_name(world, carried).unwrap_or_default();
+                    GameLog::resource_push(world, format!("The {} is a {}.", old_name, name))?;
+                }
+            }
+        }
+
+        if world.get::<RemoveCurseItem>(item).is_ok() {
+            curses_to_remove.push(user);
+        }
+
+        // Using an item reveals what it is
+        if is_user_player && identify_item(world, item) {
+            let name = world.get::<Name>(item)?;
+            GameLog::resource_push(world, format!("It was a {}.", name.as_str()))?;
+        }
+
+        if world.get::<Consumable>(item).is_ok() {
+            match world.get_mut::<Stack>(item) {
+                Ok(mut stack) if stack.quantity > 1 => stack.quantity -= 1,
+                _ => items_to_despawn.push(item),
+            }
+        }
+    }
+
+    for (user, amount) in healed {
+        spawn_number(
+            world,
+            user,
+            &format!("+{}", amount),
+            config.particle_heal.fg,
+        );
+    }
+
+    for (user, duration) in telepathy_to_grant {
+        // Drinking another potion restarts the duration rather than stacking it
+        world.insert_one(
+            user,
+            Telepathic {
+                turns_left: duration,
+            },
+        )?;
+    }
+
+    for (user, item) in equipment_to_toggle {
+        toggle_equipment(world, user, item)?;
+    }
+
+    for user in curses_to_remove {
+        remove_curses(world, user)?;
     }
 
     queue_despawn_batch(world, items_to_despawn);
@@ -122,22 +243,380 @@ pub fn drop_item_system(world: &mut World) -> anyhow::Result<()> {
     }
 
     for (dropper, item, position) in dropper_item_position {
+        if !try_unequip(world, item)? {
+            continue;
+        }
+
+        world.remove_one::<InInventory>(item)?;
         world.insert_one(item, position)?;
 
         if Some(dropper) == player {
-            let name = world.get::<Name>(item)?;
-            GameLog::resource_push(world, format!("You drop the {}.", name.as_str()))?;
+            let name = display_name(world, item).unwrap_or_default();
+            GameLog::resource_push(world, format!("You drop the {}.", name))?;
         }
     }
 
     Ok(())
 }
 
+pub fn is_visible_to_player(world: &World, entity: Entity) -> bool {
+    let pos = match world.get::<Position>(entity) {
+        Ok(pos) => *pos,
+        Err(_) => return false,
+    };
+
+    world
+        .query::<&TileMap>()
+        .into_iter()
+        .next()
+        .map(|(_, map)| map.is_tile_visible(pos.x, pos.y))
+        .unwrap_or(false)
+}
+
+/// Items carried by `owner` with their names as the player knows them, which include the quantity
+/// of stacks and whether they are equipped
 pub fn get_inventory_list(world: &World, owner: Entity) -> Vec<(Entity, String)> {
-    let mut inventory = world.query::<(&InInventory, &Name)>();
+    let mut inventory = world.query::<(&InInventory, &Name, Option<&Stack>, Option<&Equipped>)>();
     inventory
         .into_iter()
-        .filter(|(_, (in_inventory, _))| in_inventory.owner == owner)
-        .map(|(entity, (_, Name(name)))| (entity, name.to_string()))
+        .filter(|(_, (in_inventory, _, _, _))| in_inventory.owner == owner)
+        .map(|(entity, (_, _, stack, equipped))| {
+            let mut name = display_name(world, entity).unwrap_or_default();
+
+            if let Some(stack) = stack.filter(|stack| stack.quantity > 1) {
+                name = format!("{} (x{})", name, stack.quantity);
+            }
+
+            if equipped.is_some() {
+                name.push_str(" (equipped)");
+            }
+
+            (entity, name)
+        })
         .collect::<Vec<_>>()
 }
+
+/// The first item named `name` carried by `owner`
+pub fn find_inventory_item(world: &World, owner: Entity, name: &str) -> Option<Entity> {
+    world
+        .query::<(&InInventory, &Name)>()
+        .into_iter()
+        .find(|(_, (in_inventory, item_name))| {
+            in_inventory.owner == owner && item_name.as_str() == name
+        })
+        .map(|(entity, _)| entity)
+}
+
+pub fn item_quantity(world: &World, item: Entity) -> i32 {
+    world
+        .get::<Stack>(item)
+        .map(|stack| stack.quantity)
+        .unwrap_or(1)
+}
+
+/// Weight of an item, or of the whole stack
+pub fn item_weight(world: &World, item: Entity) -> f32 {
+    match world.get::<Weight>(item) {
+        Ok(weight) => weight.pounds * item_quantity(world, item) as f32,
+        Err(_) => 0.0,
+    }
+}
+
+pub fn carried_weight(world: &World, owner: Entity) -> f32 {
+    let items = world
+        .query::<&InInventory>()
+        .into_iter()
+        .filter(|(_, in_inventory)| in_inventory.owner == owner)
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    items.into_iter().map(|item| item_weight(world, item)).sum()
+}
+
+/// The stack in `owner`'s inventory which `item` would merge into
+fn matching_stack(world: &World, owner: Entity, item: Entity) -> Option<Entity> {
+    let kind = world.get::<Stack>(item).ok()?.kind;
+
+    world
+        .query::<(&InInventory, &Stack)>()
+        .into_iter()
+        .find(|(entity, (in_inventory, stack))| {
+            *entity != item && in_inventory.owner == owner && stack.kind == kind
+        })
+        .map(|(entity, _)| entity)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CarryError {
+    /// Every slot is taken, and the item doesn't stack with anything carried
+    NoSlots,
+    TooHeavy,
+}
+
+impl CarryError {
+    /// What to tell the player when they can't carry the item called `item_name`
+    pub fn message(self, item_name: &str) -> String {
+        match self {
+            CarryError::NoSlots => format!("You have no room for the {}.", item_name),
+            CarryError::TooHeavy => format!("The {} is too heavy to carry.", item_name),
+        }
+    }
+}
+
+/// Whether `owner` has room for `item`. Entities without an `InventoryCapacity` can carry anything,
+/// and no one can carry more than `MAX_LOAD_FACTOR` times their `max_weight`. Gold always fits,
+/// since it goes into the purse rather than the inventory.
+pub fn check_capacity(world: &World, owner: Entity, item: Entity) -> Result<(), CarryError> {
+    let capacity = match world.get::<InventoryCapacity>(owner) {
+        Ok(capacity) => *capacity,
+        Err(_) => return Ok(()),
+    };
+
+    if world.get::<Gold>(item).is_ok() {
+        return Ok(());
+    }
+
+    if matching_stack(world, owner, item).is_none()
+        && get_inventory_list(world, owner).len() >= capacity.max_slots
+    {
+        return Err(CarryError::NoSlots);
+    }
+
+    if carried_weight(world, owner) + item_weight(world, item)
+        > capacity.max_weight * MAX_LOAD_FACTOR
+    {
+        return Err(CarryError::TooHeavy);
+    }
+
+    Ok(())
+}
+
+/// Put `item` in `owner`'s inventory, merging it into a matching stack if there is one. Returns
+/// the entity the item ended up as.
+pub fn add_to_inventory(world: &mut World, item: Entity, owner: Entity) -> anyhow::Result<Entity> {
+    // Items handed over from another inventory have no position
+    let _ = world.remove_one::<Position>(item);
+    unindex_entity(world, item);
+
+    match matching_stack(world, owner, item) {
+        Some(stack) => {
+            let quantity = item_quantity(world, item);
+            world.get_mut::<Stack>(stack)?.quantity += quantity;
+
+            let _ = world.remove_one::<InInventory>(item);
+            queue_despawn_batch(world, Some(item));
+
+            Ok(stack)
+        }
+        None => {
+            world.insert_one(item, InInventory { owner })?;
+            Ok(item)
+        }
+    }
+}
+
+/// Take a single item out of its owner's inventory, splitting it off `item`'s stack if there is
+/// more than one. Returns the entity of the single item, which is in no inventory.
+pub fn take_one_from_inventory(
+    world: &mut World,
+    config: &Config,
+    item: Entity,
+) -> anyhow::Result<Entity> {
+    if item_quantity(world, item) <= 1 {
+        let _ = world.remove_one::<InInventory>(item);
+        return Ok(item);
+    }
+
+    // Spawn the split off item from scratch, so it has everything a new one would
+    let kind = world.get::<Stack>(item)?.kind;
+    let taken = spawner::stack_item(world, config, kind, 0, 0);
+    world.remove_one::<Position>(taken)?;
+
+    world.get_mut::<Stack>(item)?.quantity -= 1;
+
+    Ok(taken)
+}
+
+/// Encumber entities carrying more than their `InventoryCapacity` allows
+pub fn encumbrance_system(world: &mut World) -> anyhow::Result<()> {
+    let player = world.resource_entity::<Player>().ok();
+
+    let carriers = world
+        .query::<(&InventoryCapacity, Option<&Encumbered>)>()
+        .into_iter()
+        .map(|(entity, (capacity, encumbered))| (entity, capacity.max_weight, encumbered.is_some()))
+        .collect::<Vec<_>>();
+
+    for (carrier, max_weight, was_encumbered) in carriers {
+        let is_encumbered = carried_weight(world, carrier) > max_weight;
+
+        if is_encumbered == was_encumbered {
+            continue;
+        }
+
+        if is_encumbered {
+            world.insert_one(
+                carrier,
+                Encumbered {
+                    is_catching_breath: false,
+                },
+            )?;
+        } else {
+            world.remove_one::<Encumbered>(carrier)?;
+        }
+
+        if Some(carrier) == player {
+            let message = if is_encumbered {
+                "You are encumbered by your load."
+            } else {
+                "You are no longer encumbered."
+            };
+            GameLog::resource_push(world, message)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Advance the player's encumbrance by a turn, returning whether they can act in it
+pub fn player_can_act(world: &World) -> bool {
+    world
+        .query::<(&Player, &mut Encumbered)>()
+        .into_iter()
+        .next()
+        .map(|(_, (_, encumbered))| encumbered.next_turn())
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::config_from_str;
+
+    fn potion(world: &mut World) -> Entity {
+        world.spawn((
+            Name("Potion".into()),
+            Item,
+            Stack {
+                kind: StackKind::HealthPotion,
+                quantity: 1,
+            },
+            Weight { pounds: 1.0 },
+        ))
+    }
+
+    #[test]
+    fn test_stacks_merge() {
+        let mut world = World::new();
+        let owner = world.spawn((InventoryCapacity {
+            max_slots: 1,
+            max_weight: 10.0,
+        },));
+
+        let first = potion(&mut world);
+        let second = potion(&mut world);
+
+        assert_eq!(add_to_inventory(&mut world, first, owner).unwrap(), first);
+        assert_eq!(check_capacity(&world, owner, second), Ok(()));
+        assert_eq!(add_to_inventory(&mut world, second, owner).unwrap(), first);
+
+        assert_eq!(
+            get_inventory_list(&world, owner),
+            vec![(first, "Potion (x2)".to_string())]
+        );
+        assert_eq!(carried_weight(&world, owner), 2.0);
+    }
+
+    #[test]
+    fn test_take_one_from_inventory() {
+        let config = config_from_str("").unwrap();
+
+        let mut world = World::new();
+        let owner = world.spawn(());
+
+        let first = spawner::health_potion(&mut world, &config, 0, 0);
+        let second = spawner::health_potion(&mut world, &config, 0, 0);
+        add_to_inventory(&mut world, first, owner).unwrap();
+        add_to_inventory(&mut world, second, owner).unwrap();
+
+        // The split off potion is a whole new potion, with nothing missing
+        let taken = take_one_from_inventory(&mut world, &config, first).unwrap();
+        assert_ne!(taken, first);
+        assert_eq!(item_quantity(&world, first), 1);
+        assert_eq!(item_quantity(&world, taken), 1);
+        assert_eq!(world.get::<HealingItem>(taken).unwrap().heal_amount, 8);
+        assert!(world.get::<Fragile>(taken).is_ok());
+        assert!(world.get::<Position>(taken).is_err());
+        assert!(world.get::<InInventory>(taken).is_err());
+
+        let last = take_one_from_inventory(&mut world, &config, first).unwrap();
+        assert_eq!(last, first);
+        assert!(get_inventory_list(&world, owner).is_empty());
+
+        // Items only stack with items of the same kind
+        let scroll = spawner::identify_scroll(&mut world, &config, 0, 0);
+        add_to_inventory(&mut world, scroll, owner).unwrap();
+        add_to_inventory(&mut world, first, owner).unwrap();
+        assert_eq!(add_to_inventory(&mut world, taken, owner).unwrap(), first);
+        assert_eq!(get_inventory_list(&world, owner).len(), 2);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut world = World::new();
+        let owner = world.spawn((InventoryCapacity {
+            max_slots: 1,
+            max_weight: 1.0,
+        },));
+
+        let first = potion(&mut world);
+        add_to_inventory(&mut world, first, owner).unwrap();
+
+        let sword = world.spawn((Name("Sword".into()), Item, Weight { pounds: 0.5 }));
+        assert_eq!(
+            check_capacity(&world, owner, sword),
+            Err(CarryError::NoSlots)
+        );
+
+        let anvil = world.spawn((
+            Name("Potion".into()),
+            Item,
+            Stack {
+                kind: StackKind::HealthPotion,
+                quantity: 1,
+            },
+            Weight { pounds: 5.0 },
+        ));
+        assert_eq!(
+            check_capacity(&world, owner, anvil),
+            Err(CarryError::TooHeavy)
+        );
+
+        let gold = world.spawn((Name("Gold".into()), Item, Gold { amount: 10 }));
+        assert_eq!(check_capacity(&world, owner, gold), Ok(()));
+    }
+
+    #[test]
+    fn test_encumbrance() {
+        let mut world = World::new();
+        let owner = world.spawn((InventoryCapacity {
+            max_slots: 10,
+            max_weight: 1.5,
+        },));
+
+        let first = potion(&mut world);
+        add_to_inventory(&mut world, first, owner).unwrap();
+        encumbrance_system(&mut world).unwrap();
+        assert!(world.get::<Encumbered>(owner).is_err());
+
+        let second = potion(&mut world);
+        add_to_inventory(&mut world, second, owner).unwrap();
+        encumbrance_system(&mut world).unwrap();
+
+        // Encumbered carriers act every other turn
+        let mut encumbered = world.get_mut::<Encumbered>(owner).unwrap();
+        assert!(!encumbered.next_turn());
+        assert!(encumbered.next_turn());
+        assert!(!encumbered.next_turn());
+    }
+}
@@ -0,0 +1,38 @@
+use crate::prelude::*;
+
+/// Recalculate the light on every tile from lit rooms and `LightSource`s. Viewsheds are marked
+/// dirty if the lighting changed, since what can be seen depends on it.
+pub fn lighting_system(world: &mut World) {
+    let lights = world
+        .query::<(&LightSource, &Position)>()
+        .into_iter()
+        .map(|(_, (light, pos))| (*light, pos.to_point()))
+        .collect::<Vec<_>>();
+
+    let mut is_changed = false;
+
+    if let Some((_, map)) = world.query::<&mut TileMap>().into_iter().next() {
+        let previous_light = map.get_light_levels().to_vec();
+
+        map.reset_light();
+
+        for (light, origin) in lights {
+            for point in field_of_view(origin, light.radius, &*map) {
+                let distance = DistanceAlg::Pythagoras.distance2d(origin, point);
+                let intensity = 1.0 - distance / (light.radius + 1) as f32;
+
+                if intensity > 0.0 {
+                    map.add_light(point.x, point.y, light.color * intensity);
+                }
+            }
+        }
+
+        is_changed = previous_light.as_slice() != map.get_light_levels();
+    }
+
+    if is_changed {
+        for (_, viewshed) in world.query::<&mut Viewshed>().into_iter() {
+            viewshed.dirty = true;
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{identification::display_name, player::key_to_direction, prelude::*};
+
+/// Enter look mode with the cursor on the player
+pub fn start_look(world: &World) -> anyhow::Result<RunState> {
+    let player = world.resource_entity::<Player>()?;
+    let pos = *world.get::<Position>(player)?;
+
+    Ok(RunState::Look { x: pos.x, y: pos.y })
+}
+
+/// Move the look cursor, or leave look mode
+pub fn ui_look_input(context: &mut BTerm, world: &World, x: i32, y: i32) -> RunState {
+    let key = match context.key {
+        Some(key) => key,
+        None => return RunState::Look { x, y },
+    };
+
+    match key {
+        VirtualKeyCode::Escape | VirtualKeyCode::X => RunState::AwaitingInput,
+        _ => match key_to_direction(key) {
+            Some((dx, dy)) => {
+                let (width, height) = match world.query::<&TileMap>().into_iter().next() {
+                    Some((_, map)) => (map.get_width(), map.get_height()),
+                    None => return RunState::AwaitingInput,
+                };
+
+                RunState::Look {
+                    x: (x + dx).max(0).min(width - 1),
+                    y: (y + dy).max(0).min(height - 1),
+                }
+            }
+            None => RunState::Look { x, y },
+        },
+    }
+}
+
+/// Lines describing what the player knows about the tile at `(x, y)`
+pub fn describe_tile(world: &World, x: i32, y: i32) -> Vec<String> {
+    let mut map_query = world.query::<&TileMap>();
+    let map = match map_query.into_iter().next() {
+        Some((_, map)) => map,
+        None => return Vec::new(),
+    };
+
+    if !map.is_tile_revealed(x, y) {
+        return vec!["You don't know what is there.".to_string()];
+    }
+
+    let mut lines = Vec::new();
+
+    if let Some(tile) = map.get_tile(x, y) {
+        lines.push(tile.name().to_string());
+    }
+
+    if map.is_tile_visible(x, y) {
+        lines.extend(
+            map.get_entities_on_tile(x, y)
+                .iter()
+                .filter_map(|entity| describe_entity(world, *entity)),
+        );
+    } else {
+        let remembered = map.get_remembered_content(x, y);
+
+        if !remembered.is_empty() {
+            lines.push("You remember seeing:".to_string());
+            lines.extend(
+                remembered
+                    .iter()
+                    .map(|memory| format!("  {}", memory.description)),
+            );
+        }
+
+        let sensed = map
+            .get_entities_on_tile(x, y)
+            .iter()
+            .filter(|entity| world.get::<Detected>(**entity).is_ok())
+            .filter_map(|entity| describe_entity(world, *entity))
+            .collect::<Vec<_>>();
+
+        if !sensed.is_empty() {
+            lines.push("You sense:".to_string());
+            lines.extend(
+                sensed
+                    .into_iter()
+                    .map(|description| format!("  {}", description)),
+            );
+        }
+    }
+
+    lines
+}
+
+/// A one-line description of a named entity, e.g. "Orc (badly wounded)"
+pub fn describe_entity(world: &World, entity: Entity) -> Option<String> {
+    let name = display_name(world, entity)?;
+
+    let mut details = Vec::new();
+
+    if world.get::<Player>(entity).is_ok() {
+        details.push("you".to_string());
+    }
+
+    if world.get::<Vendor>(entity).is_ok() {
+        details.push("buys and sells items".to_string());
+    }
+
+    if let Ok(stats) = world.get::<CombatStats>(entity) {
+        details.push(health_description(&stats).to_string());
+    }
+
+    if let Ok(telepathic) = world.get::<Telepathic>(entity) {
+        details.push(format!("telepathic for {} turns", telepathic.turns_left));
+    }
+
+    if let Ok(poisoned) = world.get::<Poisoned>(entity) {
+        details.push(format!("poisoned for {} turns", poisoned.turns_left));
+    }
+
+    if world.get::<Encumbered>(entity).is_ok() {
+        details.push("encumbered".to_string());
+    }
+
+    details.extend(enchantment_details(world, entity));
+
+    if let Ok(healing) = world.get::<HealingItem>(entity) {
+        details.push(format!("heals {} hp", healing.heal_amount));
+    }
+
+    if let Ok(telepathy) = world.get::<TelepathyItem>(entity) {
+        details.push(format!("telepathy for {} turns", telepathy.duration));
+    }
+
+    if world.get::<MagicMappingItem>(entity).is_ok() {
+        details.push("maps the level".to_string());
+    }
+
+    if let Ok(detects) = world.get::<DetectsMonsters>(entity) {
+        details.push(format!("senses monsters within {} tiles", detects.range));
+    }
+
+    if let Ok(value) = world.get::<Value>(entity) {
+        details.push(format!("worth {} gold", value.gold));
+    }
+
+    if details.is_empty() {
+        Some(name)
+    } else {
+        Some(format!("{} ({})", name, details.join(", ")))
+    }
+}
+
+/// The bonuses of an equippable item, with its enchantment added once that is known, and what a
+/// known curse does
+fn enchantment_details(world: &World, entity: Entity) -> Vec<String> {
+    let mut details = Vec::new();
+
+    let (level, is_known) = match world.get::<Enchantment>(entity) {
+        Ok(enchantment) => (enchantment.level, enchantment.is_known),
+        Err(_) => (0, true),
+    };
+    let level = if is_known { level } else { 0 };
+
+    if let Ok(bonus) = world.get::<MeleePowerBonus>(entity) {
+        details.push(format!("{:+} power", bonus.power + level));
+    }
+
+    if let Ok(bonus) = world.get::<DefenseBonus>(entity) {
+        details.push(format!("{:+} defense", bonus.defense + level));
+    }
+
+    if !is_known {
+        details.push("unknown enchantment".to_string());
+    } else if world.get::<Cursed>(entity).is_ok() && world.get::<Enchantment>(entity).is_ok() {
+        details.push("can't be removed once equipped".to_string());
+    }
+
+    details
+}
+
+fn health_description(stats: &CombatStats) -> &'static str {
+    if stats.hp >= stats.max_hp {
+        "unhurt"
+    } else if stats.hp * 3 > stats.max_hp * 2 {
+        "lightly wounded"
+    } else if stats.hp * 3 > stats.max_hp {
+        "wounded"
+    } else if stats.hp > 0 {
+        "badly wounded"
+    } else {
+        "dead"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_entity() {
+        let mut world = World::new();
+
+        let goblin = world.spawn((
+            Name("Goblin".into()),
+            Poisoned {
+                turns_left: 3,
+                damage: 1,
+                source: None,
+            },
+            Encumbered {
+                is_catching_breath: false,
+            },
+        ));
+        assert_eq!(
+            describe_entity(&world, goblin).unwrap(),
+            "Goblin (poisoned for 3 turns, encumbered)"
+        );
+
+        let dagger = world.spawn((
+            Name("Dagger".into()),
+            MeleePowerBonus { power: 2 },
+            Enchantment {
+                level: -1,
+                is_known: false,
+            },
+            Cursed,
+        ));
+        assert_eq!(
+            describe_entity(&world, dagger).unwrap(),
+            "Dagger (+2 power, unknown enchantment)"
+        );
+
+        world.get_mut::<Enchantment>(dagger).unwrap().is_known = true;
+        assert_eq!(
+            describe_entity(&world, dagger).unwrap(),
+            "-1 Dagger (cursed) (+1 power, can't be removed once equipped)"
+        );
+    }
+}
@@ -1,32 +1,67 @@
+use anyhow::anyhow;
 use bracket_lib::random::RandomNumberGenerator;
 
+mod auto_move;
+mod bitgrid;
 mod cliopt;
+mod combat_rules;
 mod command;
 mod components;
 mod config;
 mod damage_system;
+mod decay_system;
 mod despawn_entities_system;
+mod detection_system;
+mod dialogue;
+mod equipment;
+mod faction;
 mod gamelog;
 mod gui;
+mod identification;
 mod inventory;
+mod lighting_system;
+mod look;
 mod map_indexing_system;
 mod melee_combat_system;
 mod monster_ai_system;
+mod morgue;
+mod particle;
 mod player;
 mod prelude;
+mod projectile;
+mod ranged_combat_system;
+mod replay;
 mod resource;
+mod scores;
+mod shop;
 mod spawner;
+mod status_effect_system;
+mod throwing;
 mod tilemap;
 mod visibility_system;
 
 use damage_system::damage_system;
+use decay_system::decay_system;
 use despawn_entities_system::despawn_entities_system;
-use inventory::{drop_item_system, pickup_item_system, use_item_system};
-use map_indexing_system::map_indexing_system;
+use detection_system::detection_system;
+use dialogue::{DialogueLibrary, DialogueState};
+use identification::Identification;
+use inventory::{drop_item_system, encumbrance_system, pickup_item_system, use_item_system};
+use lighting_system::lighting_system;
+use map_indexing_system::{map_indexing_system, rebuild_map_index};
 use melee_combat_system::melee_combat_system;
 use monster_ai_system::monster_ai_system;
-use player::player_input;
+use morgue::RunStats;
+use particle::Particles;
+use player::{apply_player_action, player_input, PlayerAction, PlayerInput};
 use prelude::*;
+use projectile::ProjectileAnimations;
+use ranged_combat_system::ranged_combat_system;
+use replay::{PlaybackEvent, Replay, ReplayPlayback, ReplayRecorder};
+use scores::{ScoreEntry, ScoreSort};
+use shop::ShopMode;
+use status_effect_system::status_effect_system;
+use throwing::throw_item_system;
 use visibility_system::visibility_system;
 
 const GAME_TITLE: &'static str = "Rusty-hecs Roguelike";
@@ -37,13 +72,70 @@ pub enum RunState {
     PreRun,
     PlayerTurn,
     AiTurn,
-    ShowInventory,
-    ShowDropMenu,
+    /// Inventory menus show the page `page` of the player's items
+    ShowInventory {
+        page: usize,
+    },
+    ShowDropMenu {
+        page: usize,
+    },
+    ShowThrowMenu {
+        page: usize,
+    },
+    /// Picking where to throw the item at `index` in the player's inventory list, with the cursor
+    /// at `(x, y)`
+    ThrowTarget {
+        index: usize,
+        x: i32,
+        y: i32,
+    },
+    ShowTravelMenu,
+    NextLevel,
+    Look {
+        x: i32,
+        y: i32,
+    },
+    GameOver,
+    MainMenu,
+    ShowScores {
+        sort: ScoreSort,
+    },
+    /// Trading with the vendor at `(x, y)`
+    ShowShop {
+        x: i32,
+        y: i32,
+        mode: ShopMode,
+    },
+    /// Talking to `speaker`, at the dialogue node with index `node`
+    Dialogue {
+        speaker: Entity,
+        node: usize,
+    },
+}
+
+impl RunState {
+    /// Whether this state waits for the player to act, rather than advancing the game by itself
+    fn is_waiting_for_player(self) -> bool {
+        !matches!(
+            self,
+            RunState::PreRun
+                | RunState::PlayerTurn
+                | RunState::AiTurn
+                | RunState::NextLevel
+                | RunState::GameOver
+        )
+    }
 }
 
 pub struct State {
     pub world: World,
     pub config: Config,
+    recorder: Option<ReplayRecorder>,
+    playback: Option<ReplayPlayback>,
+    /// Replaying an earlier run, whether played back on screen or headless
+    is_replay: bool,
+    /// Loaded when the high score table is opened
+    scores: Vec<ScoreEntry>,
 }
 
 fn report_system_error<T>(res: anyhow::Result<T>) {
@@ -59,29 +151,222 @@ fn report_system_error<T>(res: anyhow::Result<T>) {
 }
 
 impl State {
+    /// Advance the states which don't wait for input
+    fn advance(&mut self, run_state: RunState) -> RunState {
+        match run_state {
+            RunState::PreRun => {
+                self.run_systems();
+                RunState::AwaitingInput
+            }
+            RunState::PlayerTurn => {
+                if let Some((_, run_stats)) = self.world.query::<&mut RunStats>().into_iter().next()
+                {
+                    run_stats.turns += 1;
+                }
+                self.run_systems();
+                self.check_game_over(RunState::AiTurn)
+            }
+            RunState::AiTurn => {
+                self.run_systems();
+
+                // Encumbered players only act every other turn, so the monsters get another one
+                let next = if inventory::player_can_act(&self.world) {
+                    RunState::AwaitingInput
+                } else {
+                    RunState::PlayerTurn
+                };
+                self.check_game_over(next)
+            }
+            RunState::NextLevel => {
+                report_system_error(self.goto_next_level());
+                RunState::PreRun
+            }
+            _ => run_state,
+        }
+    }
+
+    /// Returns `GameOver` if the player has died, writing the run summary, otherwise `next`
+    fn check_game_over(&mut self, next: RunState) -> RunState {
+        let is_player_dead = match self.world.resource_entity::<Player>() {
+            Ok(player) => {
+                matches!(self.world.get::<CombatStats>(player), Ok(stats) if stats.hp <= 0)
+            }
+            Err(_) => false,
+        };
+
+        if !is_player_dead {
+            return next;
+        }
+
+        // Only runs actually played are summarized, not replays of them
+        if !self.is_replay {
+            match morgue::write_run_summary(&self.world) {
+                Ok(path) => report_system_error(
+                    GameLog::resource_push(
+                        &self.world,
+                        format!("Morgue file written to {}.", path.to_string_lossy()),
+                    )
+                    .map_err(anyhow::Error::from),
+                ),
+                res @ Err(_) => report_system_error(res),
+            }
+        }
+
+        RunState::GameOver
+    }
+
+    fn sort_scores(&mut self, sort: ScoreSort) -> RunState {
+        sort.sort(&mut self.scores);
+        RunState::ShowScores { sort }
+    }
+
+    /// Record and perform a player action
+    fn perform_action(&mut self, action: PlayerAction) -> RunState {
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(err) = recorder.record(&self.world, action) {
+                console::log(format!(
+                    "Error: Failed to record action, stopping recording: {}",
+                    err
+                ));
+                self.recorder = None;
+            }
+        }
+
+        match apply_player_action(&mut self.world, action) {
+            Ok(rs) => rs,
+            res @ Err(_) => {
+                report_system_error(res);
+                RunState::AwaitingInput
+            }
+        }
+    }
+
+    /// Take the next action from the replay being played back. When it ends, the player takes over
+    /// in whatever state it ended in.
+    fn playback_input(&mut self, context: &mut BTerm, run_state: RunState) -> RunState {
+        let playback = match &mut self.playback {
+            Some(playback) => playback,
+            None => return run_state,
+        };
+
+        let message = match playback.next_event(context, &self.world) {
+            Ok(PlaybackEvent::Action(action)) => return self.perform_action(action),
+            Ok(PlaybackEvent::Waiting) => return run_state,
+            Ok(PlaybackEvent::Finished) => "Replay finished.".to_string(),
+            Err(err) => format!("Replay stopped: {}", err),
+        };
+
+        self.playback = None;
+        report_system_error(
+            GameLog::resource_push(&self.world, message).map_err(anyhow::Error::from),
+        );
+
+        run_state
+    }
+
+    fn awaiting_input(&mut self, context: &mut BTerm) -> RunState {
+        match player_input(context, &self.world) {
+            Ok(PlayerInput::Action(action)) => self.perform_action(action),
+            Ok(PlayerInput::RunState(rs)) => rs,
+            Ok(PlayerInput::None) => RunState::AwaitingInput,
+            res @ Err(_) => {
+                report_system_error(res);
+                RunState::AwaitingInput
+            }
+        }
+    }
+
     fn run_systems(&mut self) {
         let world = &mut self.world;
 
         // Actions
-        report_system_error(use_item_system(world));
+        lighting_system(world);
         visibility_system(world);
         monster_ai_system(world);
+        report_system_error(use_item_system(world, &self.config));
+        report_system_error(throw_item_system(world, &self.config));
         report_system_error(melee_combat_system(world));
-        report_system_error(damage_system(world));
+        report_system_error(ranged_combat_system(world, &self.config));
+        report_system_error(status_effect_system(world));
+        report_system_error(damage_system(world, &self.config));
         report_system_error(pickup_item_system(world));
         report_system_error(drop_item_system(world));
+        report_system_error(encumbrance_system(world));
+        report_system_error(detection_system(world));
+        report_system_error(decay_system(world));
 
         // Cleanup
         despawn_entities_system(world);
         map_indexing_system(world);
         world.clear_commands();
     }
+
+    fn goto_next_level(&mut self) -> anyhow::Result<()> {
+        let world = &mut self.world;
+        let player = world.resource_entity::<Player>()?;
+
+        auto_move::cancel_auto_move(world);
+
+        // Everything on the old level is left behind, except what the player is carrying
+        let mut left_behind = world
+            .query::<&Position>()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .filter(|entity| *entity != player)
+            .collect::<Vec<_>>();
+
+        left_behind.extend(
+            world
+                .query::<&InInventory>()
+                .into_iter()
+                .filter(|(_, in_inventory)| in_inventory.owner != player)
+                .map(|(entity, _)| entity),
+        );
+
+        for entity in left_behind {
+            world.despawn(entity)?;
+        }
+
+        let depth = world.resource::<TileMap>()?.map(|map| map.get_depth())? + 1;
+        let map = world.resource::<RandomNumberGenerator>()?.map(|mut rng| {
+            TileMap::rooms_and_cooridors(
+                gui::MAP_VIEW_WIDTH as i32,
+                gui::MAP_VIEW_HEIGHT as i32,
+                depth,
+                &mut rng,
+            )
+        })?;
+
+        let (player_x, player_y) = map.get_center_of_first_room();
+        {
+            let mut player_pos = world.get_mut::<Position>(player)?;
+            player_pos.x = player_x;
+            player_pos.y = player_y;
+        }
+        world.get_mut::<Viewshed>(player)?.dirty = true;
+
+        spawner::rng_level_entities(world, &self.config, map.get_rooms())?;
+
+        world
+            .resource::<TileMap>()?
+            .map(|mut old_map| *old_map = map)?;
+
+        // Indexed positions of the player and their inventory refer to the old map
+        rebuild_map_index(world);
+
+        GameLog::resource_push(world, format!("You descend to level {}.", depth))?;
+
+        Ok(())
+    }
 }
 
 impl GameState for State {
     fn tick(&mut self, context: &mut BTerm) {
         context.cls_bg(self.config.bg);
 
+        projectile::animate_projectiles(&self.world, context.frame_time_ms);
+        particle::update_particles(&self.world, context.frame_time_ms);
+
         let run_state = match self.world.resource_clone::<RunState>() {
             Ok(run_state) => run_state,
             Err(_err) => {
@@ -91,61 +376,192 @@ impl GameState for State {
         };
 
         let next_run_state = match run_state {
-            RunState::PreRun => {
-                self.run_systems();
-                RunState::AwaitingInput
+            // Menus and modes are driven by the replay too, e.g. the shop a replayed action opened
+            _ if self.playback.is_some() && run_state.is_waiting_for_player() => {
+                self.playback_input(context, run_state)
             }
-            RunState::AwaitingInput => match player_input(context, &mut self.world) {
-                Ok(rs) => rs,
-                res @ Err(_) => {
-                    report_system_error(res);
-                    RunState::AwaitingInput
+            RunState::AwaitingInput => self.awaiting_input(context),
+            RunState::ShowInventory { page } => {
+                match gui::ui_inventory_menu_input(context, &self.world, page) {
+                    gui::ItemMenuResult::Cancel => RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => run_state,
+                    gui::ItemMenuResult::Page(page) => RunState::ShowInventory { page },
+                    gui::ItemMenuResult::Selected(index) => {
+                        self.perform_action(PlayerAction::UseItem { index })
+                    }
                 }
-            },
-            RunState::PlayerTurn => {
-                self.run_systems();
-                RunState::AiTurn
             }
-            RunState::AiTurn => {
-                self.run_systems();
-                RunState::AwaitingInput
+            RunState::ShowDropMenu { page } => {
+                match gui::ui_inventory_menu_input(context, &self.world, page) {
+                    gui::ItemMenuResult::Cancel => RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => run_state,
+                    gui::ItemMenuResult::Page(page) => RunState::ShowDropMenu { page },
+                    gui::ItemMenuResult::Selected(index) => {
+                        self.perform_action(PlayerAction::DropItem { index })
+                    }
+                }
             }
-            RunState::ShowInventory => {
-                match gui::ui_inventory_use_input(context, &mut self.world) {
+            RunState::ShowThrowMenu { page } => {
+                match gui::ui_inventory_menu_input(context, &self.world, page) {
                     gui::ItemMenuResult::Cancel => RunState::AwaitingInput,
-                    gui::ItemMenuResult::NoResponse => RunState::ShowInventory,
-                    gui::ItemMenuResult::Selected => RunState::PlayerTurn,
+                    gui::ItemMenuResult::NoResponse => run_state,
+                    gui::ItemMenuResult::Page(page) => RunState::ShowThrowMenu { page },
+                    gui::ItemMenuResult::Selected(index) => {
+                        match throwing::start_throw_targeting(&self.world, index) {
+                            Ok(rs) => rs,
+                            res @ Err(_) => {
+                                report_system_error(res);
+                                RunState::AwaitingInput
+                            }
+                        }
+                    }
+                }
+            }
+            RunState::ThrowTarget { index, x, y } => {
+                match throwing::ui_throw_target_input(context, &self.world, index, x, y) {
+                    PlayerInput::Action(action) => self.perform_action(action),
+                    PlayerInput::RunState(rs) => rs,
+                    PlayerInput::None => run_state,
+                }
+            }
+            RunState::ShowTravelMenu => match gui::ui_travel_menu_input(context, &self.world) {
+                gui::ItemMenuResult::Cancel => RunState::AwaitingInput,
+                gui::ItemMenuResult::NoResponse | gui::ItemMenuResult::Page(_) => {
+                    RunState::ShowTravelMenu
+                }
+                gui::ItemMenuResult::Selected(index) => {
+                    match auto_move::known_travel_destinations(&self.world).get(index) {
+                        Some((_, point)) => self.perform_action(PlayerAction::Travel {
+                            x: point.x,
+                            y: point.y,
+                        }),
+                        None => RunState::AwaitingInput,
+                    }
+                }
+            },
+            RunState::ShowShop { x, y, mode } => {
+                if context.key == Some(VirtualKeyCode::Tab) {
+                    RunState::ShowShop {
+                        x,
+                        y,
+                        mode: mode.toggle(),
+                    }
+                } else {
+                    match gui::ui_shop_menu_input(context, &self.world, x, y, mode) {
+                        gui::ItemMenuResult::Cancel => RunState::AwaitingInput,
+                        gui::ItemMenuResult::NoResponse | gui::ItemMenuResult::Page(_) => run_state,
+                        gui::ItemMenuResult::Selected(index) => match mode {
+                            ShopMode::Buy => self.perform_action(PlayerAction::Buy { x, y, index }),
+                            ShopMode::Sell => {
+                                self.perform_action(PlayerAction::Sell { x, y, index })
+                            }
+                        },
+                    }
                 }
             }
-            RunState::ShowDropMenu => {
-                match gui::ui_inventory_drop_input(context, &mut self.world) {
+            RunState::Dialogue { speaker, node } => {
+                match gui::ui_dialogue_input(context, &self.world, speaker, node) {
                     gui::ItemMenuResult::Cancel => RunState::AwaitingInput,
-                    gui::ItemMenuResult::NoResponse => RunState::ShowDropMenu,
-                    gui::ItemMenuResult::Selected => RunState::PlayerTurn,
+                    gui::ItemMenuResult::NoResponse | gui::ItemMenuResult::Page(_) => run_state,
+                    gui::ItemMenuResult::Selected(index) => {
+                        self.perform_action(PlayerAction::ChooseDialogue { index })
+                    }
                 }
             }
+            RunState::Look { x, y } => look::ui_look_input(context, &self.world, x, y),
+            RunState::GameOver => {
+                if context.key == Some(VirtualKeyCode::Escape) {
+                    context.quit();
+                }
+
+                RunState::GameOver
+            }
+            RunState::MainMenu => match gui::ui_main_menu_input(context) {
+                Some(gui::MainMenuOption::NewGame) => RunState::PreRun,
+                Some(gui::MainMenuOption::HighScores) => {
+                    self.scores = match scores::load_scores() {
+                        Ok(scores) => scores,
+                        Err(err) => {
+                            console::log(format!("Error: {}", err));
+                            Vec::new()
+                        }
+                    };
+                    self.sort_scores(ScoreSort::Depth)
+                }
+                Some(gui::MainMenuOption::Quit) => {
+                    context.quit();
+                    RunState::MainMenu
+                }
+                None => RunState::MainMenu,
+            },
+            RunState::ShowScores { sort } => match gui::ui_scores_input(context, sort) {
+                RunState::ShowScores { sort: new_sort } if new_sort != sort => {
+                    self.sort_scores(new_sort)
+                }
+                rs => rs,
+            },
+            _ => self.advance(run_state),
         };
 
-        tilemap::draw_map(context, &self.world, &self.config);
-        gui::draw_ui(context, &self.world, &self.config);
+        match run_state {
+            RunState::MainMenu => {
+                gui::draw_main_menu(context, &self.config);
+            }
+            RunState::ShowScores { sort } => {
+                gui::draw_scores(context, &self.config, &self.scores, sort);
+            }
+            _ => {
+                tilemap::draw_map(context, &self.world, &self.config);
+                gui::draw_ui(context, &self.world, &self.config);
+            }
+        }
 
         match run_state {
-            RunState::ShowInventory => {
+            RunState::ShowInventory { page } => {
                 gui::draw_inventory_menu(
                     context,
                     &mut self.world,
                     &self.config,
                     "Use from Inventory",
+                    page,
                 );
             }
-            RunState::ShowDropMenu => {
+            RunState::ShowDropMenu { page } => {
                 gui::draw_inventory_menu(
                     context,
                     &mut self.world,
                     &self.config,
                     "Drop from Inventory",
+                    page,
+                );
+            }
+            RunState::ShowThrowMenu { page } => {
+                gui::draw_inventory_menu(
+                    context,
+                    &mut self.world,
+                    &self.config,
+                    "Throw which item?",
+                    page,
                 );
             }
+            RunState::ThrowTarget { index, x, y } => {
+                gui::draw_throw_target(context, &self.world, &self.config, index, x, y);
+            }
+            RunState::ShowTravelMenu => {
+                gui::draw_travel_menu(context, &self.world, &self.config);
+            }
+            RunState::ShowShop { x, y, mode } => {
+                gui::draw_shop_menu(context, &self.world, &self.config, x, y, mode);
+            }
+            RunState::Dialogue { speaker, node } => {
+                gui::draw_dialogue(context, &self.world, &self.config, speaker, node);
+            }
+            RunState::Look { x, y } => {
+                gui::draw_look(context, &self.world, &self.config, x, y);
+            }
+            RunState::GameOver => {
+                gui::draw_game_over(context, &self.world, &self.config);
+            }
             _ => {}
         }
 
@@ -155,39 +571,133 @@ impl GameState for State {
     }
 }
 
-fn main() -> BError {
-    let opts = cliopt::parse_opt_args()?;
-    let config = opts.config;
+/// Create the world for a new game. Everything random in the game comes from the world RNG
+/// seeded here, so the same seed and actions always play out the same way.
+fn new_game_world(config: &Config, seed: u64) -> anyhow::Result<World> {
+    let mut rng = RandomNumberGenerator::seeded(seed);
 
     // Generate map
-    let map = TileMap::rooms_and_cooridors(gui::MAP_VIEW_WIDTH as i32, gui::MAP_VIEW_HEIGHT as i32);
+    let map = TileMap::rooms_and_cooridors(
+        gui::MAP_VIEW_WIDTH as i32,
+        gui::MAP_VIEW_HEIGHT as i32,
+        1,
+        &mut rng,
+    );
 
     // Create ECS World
     let mut world = World::new();
 
     // Add RNG
-    world.spawn_resource(RandomNumberGenerator::new(), ())?;
+    world.spawn_resource(rng, ())?;
+
+    // Spawn Item Appearances
+    let identification = world
+        .resource::<RandomNumberGenerator>()?
+        .map(|mut rng| Identification::new(&mut rng))?;
+    world.spawn_resource(identification, ())?;
 
     // Spawn Run State
     world.spawn_resource(RunState::PreRun, ())?;
 
     // Spawn Player
     let (player_x, player_y) = map.get_center_of_first_room();
-    spawner::player(&mut world, &config, player_x, player_y)?;
+    spawner::player(&mut world, config, player_x, player_y)?;
 
     // Spawn Monsters and Items
-    spawner::health_potion(&mut world, &config, player_x + 1, player_y);
-    for room in map.get_rooms().iter().skip(1) {
-        spawner::rng_room_entities(&mut world, &config, room)?;
-    }
+    spawner::health_potion(&mut world, config, player_x + 1, player_y);
+    spawner::rng_level_entities(&mut world, config, map.get_rooms())?;
 
     // Spawn Map
     world.spawn_resource(map, ())?;
 
+    // Spawn Dialogue
+    world.spawn_resource(DialogueLibrary::load_default()?, ())?;
+    world.spawn_resource(DialogueState::default(), ())?;
+
+    // Spawn Run Stats
+    world.spawn_resource(RunStats::new(seed), ())?;
+
     // Spawn Game Log
     world.spawn_resource(GameLog::new(), ())?;
+
+    // Spawn Effects
+    world.spawn_resource(ProjectileAnimations::default(), ())?;
+    world.spawn_resource(Particles::default(), ())?;
     GameLog::resource_push(&world, format!("Welcome to {}", GAME_TITLE))?;
 
+    Ok(world)
+}
+
+fn main() -> BError {
+    let opts = cliopt::parse_opt_args()?;
+    let config = opts.config;
+
+    let replay = match &opts.replay {
+        Some(path) => Some(Replay::load(path)?),
+        None => None,
+    };
+
+    let seed = match &replay {
+        Some(replay) => replay.seed(),
+        None => opts
+            .seed
+            .unwrap_or_else(|| RandomNumberGenerator::new().next_u64()),
+    };
+
+    let world = new_game_world(&config, seed)?;
+
+    if opts.headless {
+        let replay = replay.ok_or_else(|| anyhow!("--headless requires --replay"))?;
+
+        let mut state = State {
+            world,
+            config,
+            recorder: None,
+            playback: None,
+            is_replay: true,
+            scores: Vec::new(),
+        };
+
+        let (actions, final_hash) = replay::run_headless(&mut state, replay)?;
+        println!(
+            "Replayed {} actions. Final state hash: {:016x}",
+            actions, final_hash
+        );
+
+        return match opts.expect_hash {
+            Some(expected) if expected != final_hash => Err(anyhow!(
+                "Final state hash {:016x} does not match expected {:016x}",
+                final_hash,
+                expected
+            )
+            .into()),
+            _ => Ok(()),
+        };
+    }
+
+    // Record new games, so they can be replayed to reproduce bugs
+    let recorder = match replay {
+        Some(_) => None,
+        None => match ReplayRecorder::create(&opts.record, seed) {
+            Ok(recorder) => Some(recorder),
+            Err(err) => {
+                console::log(format!("Warning: Not recording replay: {}", err));
+                None
+            }
+        },
+    };
+
+    // New games start at the main menu, replays start playing right away
+    if replay.is_none() {
+        world
+            .resource::<RunState>()?
+            .map(|mut run_state| *run_state = RunState::MainMenu)?;
+    }
+
+    let is_replay = replay.is_some();
+    let replay_speed = opts.replay_speed;
+    let playback = replay.map(|replay| ReplayPlayback::new(replay, replay_speed));
+
     // Create terminal context
     let mut context = BTermBuilder::simple80x50().with_title(GAME_TITLE).build()?;
 
@@ -200,7 +710,14 @@ fn main() -> BError {
     }
 
     // Create State
-    let state = State { world, config };
+    let state = State {
+        world,
+        config,
+        recorder,
+        playback,
+        is_replay,
+        scores: Vec::new(),
+    };
 
     // Start main loop
     main_loop(context, state)
@@ -1,16 +1,341 @@
-use crate::prelude::*;
+use crate::{look::describe_entity, prelude::*, tilemap::RememberedEntity};
 
-pub fn map_indexing_system(world: &World) {
-    for (_, map) in world.query::<&mut TileMap>().into_iter() {
-        map.reset_blocked_tiles();
+/// Index entities which were given a `Position` since the last run, e.g. newly spawned or dropped
+/// ones. Everything else keeps the index up to date as it happens: moves go through `move_entity`,
+/// and pickups and despawns through `unindex_entity`.
+pub fn map_indexing_system(world: &mut World) {
+    // Only entities without an `IndexedPosition` are visited, not every positioned entity
+    let placed = world
+        .query::<(&Position, Option<&BlocksTile>)>()
+        .without::<IndexedPosition>()
+        .into_iter()
+        .map(|(entity, (pos, blocks_tile))| {
+            (
+                entity,
+                IndexedPosition {
+                    position: *pos,
+                    blocks_tile: blocks_tile.is_some(),
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if let Some((_, map)) = world.query::<&mut TileMap>().into_iter().next() {
+        for (entity, indexed) in placed.iter() {
+            map.index_entity(
+                indexed.position.x,
+                indexed.position.y,
+                *entity,
+                indexed.blocks_tile,
+            );
+        }
+    }
+
+    for (entity, indexed) in placed {
+        let _ = world.insert_one(entity, indexed);
+    }
+
+    remember_visible_content(world);
+}
+
+/// Move an entity to `to`, updating the `TileMap` index straight away so nothing else can step
+/// onto the tile it moved to in the same turn
+pub fn move_entity(world: &World, entity: Entity, to: Position) -> anyhow::Result<()> {
+    *world.get_mut::<Position>(entity)? = to;
+
+    if let Ok(mut indexed) = world.get_mut::<IndexedPosition>(entity) {
+        let moved = IndexedPosition {
+            position: to,
+            ..*indexed
+        };
+
+        if let Some((_, map)) = world.query::<&mut TileMap>().into_iter().next() {
+            map.move_indexed_entity(entity, *indexed, moved);
+        }
+
+        *indexed = moved;
+    }
+
+    Ok(())
+}
+
+/// Remove an entity from the `TileMap` index, e.g. when it is picked up or despawned
+pub fn unindex_entity(world: &mut World, entity: Entity) {
+    let indexed = match world.remove_one::<IndexedPosition>(entity) {
+        Ok(indexed) => indexed,
+        Err(_) => return,
+    };
+
+    if let Some((_, map)) = world.query::<&mut TileMap>().into_iter().next() {
+        map.unindex_entity(
+            indexed.position.x,
+            indexed.position.y,
+            entity,
+            indexed.blocks_tile,
+        );
+    }
+}
+
+/// Stop an entity blocking its tile, e.g. as it dies
+pub fn unblock_tile(world: &mut World, entity: Entity) {
+    let _ = world.remove_one::<BlocksTile>(entity);
+
+    if let Ok(mut indexed) = world.get_mut::<IndexedPosition>(entity) {
+        let unblocked = IndexedPosition {
+            blocks_tile: false,
+            ..*indexed
+        };
+
+        if let Some((_, map)) = world.query::<&mut TileMap>().into_iter().next() {
+            map.move_indexed_entity(entity, *indexed, unblocked);
+        }
+
+        *indexed = unblocked;
+    }
+}
+
+/// Rebuild the whole index from scratch, e.g. after the `TileMap` is replaced
+pub fn rebuild_map_index(world: &mut World) {
+    let mut to_insert = Vec::new();
+
+    if let Some((_, map)) = world.query::<&mut TileMap>().into_iter().next() {
         map.clear_content_index();
 
-        for (_, (_, position)) in world.query::<(&BlocksTile, &Position)>().into_iter() {
-            map.set_tile_blocked(position.x, position.y, true);
+        for (entity, (pos, blocks_tile, indexed)) in world
+            .query::<(&Position, Option<&BlocksTile>, Option<&mut IndexedPosition>)>()
+            .into_iter()
+        {
+            let current = IndexedPosition {
+                position: *pos,
+                blocks_tile: blocks_tile.is_some(),
+            };
+
+            map.index_entity(pos.x, pos.y, entity, current.blocks_tile);
+
+            match indexed {
+                Some(indexed) => *indexed = current,
+                None => to_insert.push((entity, current)),
+            }
         }
+    }
 
-        for (entity, position) in world.query::<&Position>().into_iter() {
-            map.add_entity_to_tile_content(position.x, position.y, entity);
+    let to_remove = world
+        .query::<&IndexedPosition>()
+        .without::<Position>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    for (entity, indexed) in to_insert {
+        let _ = world.insert_one(entity, indexed);
+    }
+
+    for entity in to_remove {
+        let _ = world.remove_one::<IndexedPosition>(entity);
+    }
+
+    remember_visible_content(world);
+}
+
+fn remember_visible_content(world: &World) {
+    if let Some((_, map)) = world.query::<&mut TileMap>().into_iter().next() {
+        map.remember_visible_content(|entity| {
+            let description = describe_entity(world, entity)?;
+
+            let is_mobile =
+                world.get::<Monster>(entity).is_ok() || world.get::<Player>(entity).is_ok();
+            let renderable = if is_mobile {
+                None
+            } else {
+                world.get::<Renderable>(entity).ok().map(|r| r.clone())
+            };
+
+            Some(RememberedEntity {
+                description,
+                renderable,
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bracket_lib::random::RandomNumberGenerator;
+    use std::time::{Duration, Instant};
+
+    fn index_snapshot(world: &World) -> Vec<(Vec<Entity>, bool)> {
+        world
+            .resource::<TileMap>()
+            .unwrap()
+            .map(|map| {
+                (0..map.get_height())
+                    .flat_map(|y| (0..map.get_width()).map(move |x| (x, y)))
+                    .map(|(x, y)| {
+                        let mut entities = map.get_entities_on_tile(x, y).to_vec();
+                        entities.sort();
+                        (entities, map.is_tile_blocked(x, y))
+                    })
+                    .collect()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_index_follows_moves_pickups_and_despawns() {
+        let mut world = World::new();
+        world
+            .spawn_resource(
+                TileMap::from_ascii(&["#####", "#...#", "#...#", "#...#", "#####"]),
+                (),
+            )
+            .unwrap();
+
+        let (x, y) = (2, 2);
+
+        let monster = world.spawn((Monster, Position { x, y }, BlocksTile));
+        let item = world.spawn((Item, Position { x: x + 1, y }));
+        let corpse = world.spawn((Item, Position { x, y: y + 1 }, BlocksTile));
+        map_indexing_system(&mut world);
+
+        move_entity(&world, monster, Position { x: x - 1, y }).unwrap();
+        world.remove_one::<Position>(item).unwrap();
+        unindex_entity(&mut world, item);
+        unblock_tile(&mut world, corpse);
+        let dropped = world.spawn((Item, Position { x, y }));
+
+        let is_blocked = |world: &World, x, y| {
+            world
+                .resource::<TileMap>()
+                .unwrap()
+                .map(|map| map.is_tile_blocked(x, y))
+                .unwrap()
+        };
+        assert!(is_blocked(&world, x - 1, y));
+        assert!(!is_blocked(&world, x, y));
+        assert!(!is_blocked(&world, x, y + 1));
+
+        map_indexing_system(&mut world);
+        assert!(world.get::<IndexedPosition>(dropped).is_ok());
+
+        let index = index_snapshot(&world);
+        rebuild_map_index(&mut world);
+        assert!(index == index_snapshot(&world));
+    }
+
+    #[test]
+    fn test_remembered_content_follows_view_and_content_changes() {
+        let mut world = World::new();
+        world
+            .spawn_resource(TileMap::from_ascii(&["###", "#.#", "###"]), ())
+            .unwrap();
+
+        let set_visible = |world: &World, visible: bool| {
+            world
+                .resource::<TileMap>()
+                .unwrap()
+                .map(|mut map| {
+                    map.clear_visible_tiles();
+                    if visible {
+                        map.set_tile_visible(1, 1);
+                    }
+                })
+                .unwrap();
+        };
+        let remembered = |world: &World| {
+            world
+                .resource::<TileMap>()
+                .unwrap()
+                .map(|map| {
+                    map.get_remembered_content(1, 1)
+                        .iter()
+                        .map(|r| r.description.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap()
+        };
+
+        set_visible(&world, true);
+        let dagger = world.spawn((Item, Name("Dagger".into()), Position { x: 1, y: 1 }));
+        map_indexing_system(&mut world);
+        assert_eq!(remembered(&world), vec!["Dagger".to_string()]);
+
+        // Out of view, the dagger is remembered even after it is gone
+        set_visible(&world, false);
+        world.remove_one::<Position>(dagger).unwrap();
+        unindex_entity(&mut world, dagger);
+        map_indexing_system(&mut world);
+        assert_eq!(remembered(&world), vec!["Dagger".to_string()]);
+
+        set_visible(&world, true);
+        map_indexing_system(&mut world);
+        assert!(remembered(&world).is_empty());
+
+        world.spawn((Item, Name("Shield".into()), Position { x: 1, y: 1 }));
+        map_indexing_system(&mut world);
+        assert_eq!(remembered(&world), vec!["Shield".to_string()]);
+    }
+
+    /// Compares keeping the index up to date as things move against rebuilding it on every run, as
+    /// was done before. Run with `cargo test --release -- --ignored --nocapture` to see the
+    /// timings.
+    #[test]
+    #[ignore]
+    fn bench_incremental_indexing_against_full_rebuild() {
+        const NUM_MONSTERS: usize = 2000;
+        const NUM_RUNS: usize = 60;
+
+        let mut world = World::new();
+        let mut rng = RandomNumberGenerator::seeded(34);
+
+        let map = TileMap::rooms_and_cooridors(400, 200, 1, &mut rng);
+        let rooms = map.get_rooms().to_vec();
+        world.spawn_resource(map, ()).unwrap();
+
+        let random_floor = |rng: &mut RandomNumberGenerator| {
+            let room = &rooms[rng.range(0, rooms.len() as i32) as usize];
+            Position {
+                x: rng.range(room.x1 + 1, room.x2 + 1),
+                y: rng.range(room.y1 + 1, room.y2 + 1),
+            }
+        };
+
+        let monsters = (0..NUM_MONSTERS)
+            .map(|_| world.spawn((Monster, random_floor(&mut rng), BlocksTile)))
+            .collect::<Vec<_>>();
+
+        map_indexing_system(&mut world);
+
+        let mut incremental = Duration::default();
+        let mut full_rebuild = Duration::default();
+
+        for _ in 0..NUM_RUNS {
+            // Roughly what happens between runs: some of the monsters move
+            let moves = monsters
+                .iter()
+                .step_by(7)
+                .map(|monster| (*monster, random_floor(&mut rng)))
+                .collect::<Vec<_>>();
+
+            let start = Instant::now();
+            for (monster, to) in moves {
+                move_entity(&world, monster, to).unwrap();
+            }
+            map_indexing_system(&mut world);
+            incremental += start.elapsed();
+
+            let incremental_index = index_snapshot(&world);
+
+            let start = Instant::now();
+            rebuild_map_index(&mut world);
+            full_rebuild += start.elapsed();
+
+            assert!(incremental_index == index_snapshot(&world));
         }
+
+        println!(
+            "{} runs with {} monsters: incremental {:?}, full rebuild {:?}",
+            NUM_RUNS, NUM_MONSTERS, incremental, full_rebuild
+        );
     }
 }
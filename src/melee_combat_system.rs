@@ -1,4 +1,10 @@
-use crate::prelude::*;
+use bracket_lib::random::RandomNumberGenerator;
+
+use crate::{
+    combat_rules::{roll_power_attack, AttackResult},
+    equipment::equipment_bonus,
+    prelude::*,
+};
 
 pub fn melee_combat_system(world: &mut World) -> anyhow::Result<()> {
     let mut damage_commands_batch = Vec::new();
@@ -31,33 +37,48 @@ pub fn melee_combat_system(world: &mut World) -> anyhow::Result<()> {
         let (defender_stats, defender_name) = defender_query.get().expect("Unfiltered query");
 
         if attacker_stats.hp > 0 && defender_stats.hp > 0 {
-            let damage = i32::max(0, attacker_stats.power - defender_stats.defense);
+            let power = attacker_stats.power + equipment_bonus(world, cmd.attacker).power;
+            let defense = defender_stats.defense + equipment_bonus(world, cmd.defender).defense;
+            let result = world
+                .resource::<RandomNumberGenerator>()?
+                .map(|mut rng| roll_power_attack(&mut rng, power, defense))?;
 
-            let (attacker_name, is_are, defender_name) = if Some(cmd.attacker) == player_entity {
-                ("You", "are", defender_name.0.as_str())
+            let (attacker_name, defender_name) = if Some(cmd.attacker) == player_entity {
+                ("You", defender_name.0.as_str())
             } else if Some(cmd.defender) == player_entity {
-                (attacker_name.0.as_str(), "is", "you")
+                (attacker_name.0.as_str(), "you")
             } else {
-                (attacker_name.0.as_str(), "is", defender_name.0.as_str())
+                (attacker_name.0.as_str(), defender_name.0.as_str())
             };
 
-            if damage > 0 {
-                GameLog::resource_push(
-                    world,
+            let (message, damage) = match result {
+                AttackResult::Fumble => (
+                    format!("{} fumbled the attack on {}.", attacker_name, defender_name),
+                    0,
+                ),
+                AttackResult::Miss => (format!("{} missed {}.", attacker_name, defender_name), 0),
+                AttackResult::Hit { damage } => (
                     format!("{} hit {} for {} hp.", attacker_name, defender_name, damage),
-                )?;
+                    damage,
+                ),
+                AttackResult::Critical { damage } => (
+                    format!(
+                        "{} critically hit {} for {} hp!",
+                        attacker_name, defender_name, damage
+                    ),
+                    damage,
+                ),
+            };
+
+            GameLog::resource_push_combat(world, message)?;
+
+            if damage > 0 {
                 damage_commands_batch.push(DamageCommand {
                     entity: cmd.defender,
                     amount: damage,
+                    damage_type: DamageType::Physical,
+                    source: Some(cmd.attacker),
                 })
-            } else {
-                GameLog::resource_push(
-                    world,
-                    format!(
-                        "{} {} unable to hurt {}.",
-                        attacker_name, is_are, defender_name
-                    ),
-                )?;
             }
         }
     }
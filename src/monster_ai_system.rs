@@ -1,4 +1,9 @@
-use crate::prelude::*;
+use crate::{
+    faction::{reaction, Reaction},
+    prelude::*,
+    projectile::line_of_fire,
+    tilemap::GoalTileMap,
+};
 
 pub fn monster_ai_system(world: &mut World) {
     match world.resource_clone::<RunState>() {
@@ -7,74 +12,243 @@ pub fn monster_ai_system(world: &mut World) {
     }
 
     let mut attack_cmd_batch = Vec::new();
+    let mut ranged_attack_cmd_batch = Vec::new();
+    let mut use_item_cmd_batch = Vec::new();
+
+    // Anything alive can be attacked, by a monster which is hostile to it
+    let targets = world
+        .query::<(&Position, &CombatStats, &Faction)>()
+        .into_iter()
+        .filter(|(_, (_, stats, _))| stats.hp > 0)
+        .map(|(entity, (pos, _, faction))| (entity, *pos, *faction))
+        .collect::<Vec<_>>();
 
     {
-        if let Some((player_entity, (_, player_pos, _player_name))) = world
-            .query::<(&Player, &Position, &Name)>()
-            .into_iter()
-            .next()
-        {
-            if let Some((_, mut map)) = world.query::<&mut TileMap>().into_iter().next() {
-                if let Some(player_pos_index) = map.get_index(player_pos.x, player_pos.y) {
-                    for (monster_entity, (_, monster_viewshed, _monster_name, monster_pos)) in world
-                        .query::<(&Monster, &mut Viewshed, &Name, &mut Position)>()
-                        .into_iter()
-                    {
-                        monster_ai_to_player(
-                            &mut attack_cmd_batch,
-                            &mut map,
-                            monster_entity,
-                            monster_pos,
-                            monster_viewshed,
-                            player_entity,
-                            player_pos,
-                            player_pos_index,
-                        )
+        if let Some((_, mut map)) = world.query::<&mut TileMap>().into_iter().next() {
+            for (
+                monster_entity,
+                (
+                    _,
+                    monster_viewshed,
+                    monster_pos,
+                    mut monster_indexed,
+                    monster_faction,
+                    monster_encumbered,
+                    monster_stats,
+                    monster_ranged,
+                ),
+            ) in world
+                .query::<(
+                    &Monster,
+                    &mut Viewshed,
+                    &mut Position,
+                    Option<&mut IndexedPosition>,
+                    &Faction,
+                    Option<&mut Encumbered>,
+                    &CombatStats,
+                    Option<&RangedAttack>,
+                )>()
+                .into_iter()
+            {
+                // Encumbered monsters only act every other turn
+                if let Some(encumbered) = monster_encumbered {
+                    if !encumbered.next_turn() {
+                        continue;
+                    }
+                }
+
+                // Badly hurt monsters drink a healing potion if they carry one, instead of moving
+                if monster_stats.hp < monster_stats.max_hp / 2 {
+                    if let Some(item) = carried_healing_item(world, monster_entity) {
+                        use_item_cmd_batch.push(UseItemCommand {
+                            user: monster_entity,
+                            item,
+                        });
+                        continue;
+                    }
+                }
+
+                let target = targets
+                    .iter()
+                    .filter(|(entity, target_pos, target_faction)| {
+                        *entity != monster_entity
+                            && reaction(*monster_faction, *target_faction) == Reaction::Hostile
+                            && monster_viewshed
+                                .visible_tiles
+                                .contains(target_pos.to_point())
+                    })
+                    .map(|(entity, target_pos, _)| {
+                        let distance = DistanceAlg::Pythagoras
+                            .distance2d(monster_pos.to_point(), target_pos.to_point());
+                        (*entity, *target_pos, distance)
+                    })
+                    .min_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap());
+
+                if let Some((target_entity, target_pos, distance)) = target {
+                    if let Some(ranged) = monster_ranged {
+                        // Back away from targets which are too close, if there is room to
+                        if distance < ranged.min_distance
+                            && step_away(
+                                map,
+                                monster_entity,
+                                monster_pos,
+                                monster_indexed.as_deref_mut(),
+                                monster_viewshed,
+                                &target_pos,
+                            )
+                        {
+                            continue;
+                        }
+
+                        // Only shoot when nothing else is in the line of fire
+                        if distance <= ranged.range
+                            && line_of_fire(
+                                world,
+                                map,
+                                monster_pos.to_point(),
+                                target_pos.to_point(),
+                            )
+                            .1 == Some(target_entity)
+                        {
+                            ranged_attack_cmd_batch.push(InitiateRangedAttackCommand {
+                                attacker: monster_entity,
+                                defender: target_entity,
+                            });
+                            continue;
+                        }
                     }
+
+                    monster_ai_to_target(
+                        &mut attack_cmd_batch,
+                        &mut map,
+                        monster_entity,
+                        monster_pos,
+                        monster_indexed,
+                        monster_viewshed,
+                        target_entity,
+                        &target_pos,
+                        distance,
+                    )
                 }
             }
         }
     }
 
     world.spawn_batch_commands(attack_cmd_batch);
+    world.spawn_batch_commands(ranged_attack_cmd_batch);
+    world.spawn_batch_commands(use_item_cmd_batch);
+}
+
+fn carried_healing_item(world: &World, owner: Entity) -> Option<Entity> {
+    world
+        .query::<(&InInventory, &HealingItem)>()
+        .into_iter()
+        .find(|(_, (in_inventory, _))| in_inventory.owner == owner)
+        .map(|(item, _)| item)
 }
 
-fn monster_ai_to_player(
+fn monster_ai_to_target(
     attack_cmd_batch: &mut Vec<InitiateAttackCommand>,
     map: &mut TileMap,
     monster_entity: Entity,
     monster_pos: &mut Position,
+    monster_indexed: Option<&mut IndexedPosition>,
     monster_viewshed: &mut Viewshed,
-    player_entity: Entity,
-    player_pos: &Position,
-    player_pos_index: usize,
+    target_entity: Entity,
+    target_pos: &Position,
+    distance_to_target: f32,
 ) {
-    if monster_viewshed
-        .visible_tiles
-        .contains(&player_pos.to_point())
-    {
-        let distance_to_player =
-            DistanceAlg::Pythagoras.distance2d(monster_pos.to_point(), player_pos.to_point());
-        if distance_to_player < 1.5 {
-            attack_cmd_batch.push(InitiateAttackCommand {
-                attacker: monster_entity,
-                defender: player_entity,
-            });
-        } else {
-            if let Some(start) = map.get_index(monster_pos.x, monster_pos.y) {
-                let nav = a_star_search(start, player_pos_index, &mut *map);
-
-                if nav.success {
-                    if let Some(i) = nav.steps.get(1) {
-                        let (next_x, next_y) = map.get_coords(*i);
-                        map.set_tile_blocked(monster_pos.x, monster_pos.y, false);
-                        monster_pos.x = next_x;
-                        monster_pos.y = next_y;
-                        monster_viewshed.dirty = true;
-                        map.set_tile_blocked(monster_pos.x, monster_pos.y, true);
-                    }
+    if distance_to_target < 1.5 {
+        attack_cmd_batch.push(InitiateAttackCommand {
+            attacker: monster_entity,
+            defender: target_entity,
+        });
+    } else {
+        let start = map.get_index(monster_pos.x, monster_pos.y);
+        let goal = map.get_index(target_pos.x, target_pos.y);
+
+        if let (Some(start), Some(goal)) = (start, goal) {
+            // The target blocks its own tile if it is a monster, so path to it as if it didn't
+            let nav = a_star_search(start, goal, &GoalTileMap { map, goal });
+
+            if nav.success {
+                if let Some(i) = nav.steps.get(1) {
+                    let (next_x, next_y) = map.get_coords(*i);
+                    move_monster(
+                        map,
+                        monster_entity,
+                        monster_pos,
+                        monster_indexed,
+                        monster_viewshed,
+                        next_x,
+                        next_y,
+                    );
                 }
             }
         }
     }
 }
+
+/// Step to the neighboring tile farthest from `target_pos`, if one is farther than where the
+/// monster stands. Returns whether the monster moved.
+fn step_away(
+    map: &mut TileMap,
+    monster_entity: Entity,
+    monster_pos: &mut Position,
+    monster_indexed: Option<&mut IndexedPosition>,
+    monster_viewshed: &mut Viewshed,
+    target_pos: &Position,
+) -> bool {
+    let distance_from =
+        |point: Point| DistanceAlg::Pythagoras.distance2d(point, target_pos.to_point());
+    let current_distance = distance_from(monster_pos.to_point());
+
+    let farthest = (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+        .filter(|(dx, dy)| (*dx, *dy) != (0, 0))
+        .map(|(dx, dy)| Point::new(monster_pos.x + dx, monster_pos.y + dy))
+        .filter(|point| !map.is_tile_blocked(point.x, point.y))
+        .map(|point| (point, distance_from(point)))
+        .filter(|(_, distance)| *distance > current_distance)
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+
+    match farthest {
+        Some((point, _)) => {
+            move_monster(
+                map,
+                monster_entity,
+                monster_pos,
+                monster_indexed,
+                monster_viewshed,
+                point.x,
+                point.y,
+            );
+            true
+        }
+        None => false,
+    }
+}
+
+fn move_monster(
+    map: &mut TileMap,
+    monster_entity: Entity,
+    monster_pos: &mut Position,
+    monster_indexed: Option<&mut IndexedPosition>,
+    monster_viewshed: &mut Viewshed,
+    x: i32,
+    y: i32,
+) {
+    monster_pos.x = x;
+    monster_pos.y = y;
+    monster_viewshed.dirty = true;
+
+    // Update the index now, so other monsters can't step onto the same tile
+    if let Some(indexed) = monster_indexed {
+        let moved = IndexedPosition {
+            position: *monster_pos,
+            ..*indexed
+        };
+        map.move_indexed_entity(monster_entity, *indexed, moved);
+        *indexed = moved;
+    }
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    inventory::get_inventory_list,
+    prelude::*,
+    scores::{self, ScoreEntry},
+    GAME_TITLE,
+};
+
+/// Directory inside the data directory
+const MORGUE_DIR_NAME: &str = "morgue";
+
+/// Number of game log messages included in the morgue file
+const MORGUE_LOG_LENGTH: usize = 20;
+
+/// Statistics about the current run, for the morgue file and high scores
+#[derive(Debug)]
+pub struct RunStats {
+    pub seed: u64,
+    pub turns: u32,
+    pub xp: u32,
+    /// Monsters the player killed, by name
+    pub kills: BTreeMap<String, u32>,
+    pub cause_of_death: Option<String>,
+}
+
+impl RunStats {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            turns: 0,
+            xp: 0,
+            kills: BTreeMap::new(),
+            cause_of_death: None,
+        }
+    }
+}
+
+/// Write a morgue file for the finished run and add it to the scores. Returns the path of the
+/// morgue file.
+pub fn write_run_summary(world: &World) -> anyhow::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    let seed = world
+        .resource::<RunStats>()?
+        .map(|run_stats| run_stats.seed)?;
+
+    let morgue_dir = scores::data_dir().join(MORGUE_DIR_NAME);
+    fs::create_dir_all(&morgue_dir)?;
+    let (morgue_path, mut file) = create_morgue_file(&morgue_dir, timestamp, seed)?;
+    file.write_all(morgue_text(world)?.as_bytes())?;
+
+    let depth = world.resource::<TileMap>()?.map(|map| map.get_depth())?;
+
+    let entry = world.resource::<RunStats>()?.map(|run_stats| ScoreEntry {
+        timestamp,
+        seed,
+        depth,
+        xp: run_stats.xp,
+        turns: run_stats.turns,
+        kills: run_stats.kills.values().sum(),
+        cause_of_death: run_stats.cause_of_death.clone(),
+    })?;
+
+    scores::append_score(&entry)?;
+
+    Ok(morgue_path)
+}
+
+/// Create a new morgue file named after the time and seed of the run, numbering it if there
+/// already is one by that name, so no earlier morgue file is overwritten
+fn create_morgue_file(dir: &Path, timestamp: u64, seed: u64) -> io::Result<(PathBuf, File)> {
+    let mut number = 1;
+
+    loop {
+        let file_name = if number == 1 {
+            format!("morgue-{}-{}.txt", timestamp, seed)
+        } else {
+            format!("morgue-{}-{}-{}.txt", timestamp, seed, number)
+        };
+        let path = dir.join(file_name);
+
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => number += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn morgue_text(world: &World) -> anyhow::Result<String> {
+    let player = world.resource_entity::<Player>()?;
+    let stats = *world.get::<CombatStats>(player)?;
+    let player_pos = *world.get::<Position>(player)?;
+
+    let mut text = String::new();
+
+    writeln!(text, "{} morgue file", GAME_TITLE)?;
+    writeln!(text)?;
+
+    world
+        .resource::<RunStats>()?
+        .map(|run_stats| -> std::fmt::Result {
+            writeln!(text, "Seed: {}", run_stats.seed)?;
+            writeln!(
+                text,
+                "{} after {} turns.",
+                run_stats.cause_of_death.as_deref().unwrap_or("Still alive"),
+                run_stats.turns
+            )?;
+            writeln!(text, "Experience: {}", run_stats.xp)?;
+            writeln!(text)?;
+            writeln!(text, "Kills:")?;
+
+            let mut kills = run_stats.kills.iter().collect::<Vec<_>>();
+            kills.sort_by(|(a_name, a_count), (b_name, b_count)| {
+                b_count.cmp(a_count).then(a_name.cmp(b_name))
+            });
+
+            if kills.is_empty() {
+                writeln!(text, "  None")?;
+            }
+
+            for (name, count) in kills {
+                writeln!(text, "  {:>3} {}", count, name)?;
+            }
+
+            Ok(())
+        })??;
+
+    writeln!(text)?;
+    writeln!(
+        text,
+        "HP: {}/{}  Power: {}  Defense: {}",
+        stats.hp, stats.max_hp, stats.power, stats.defense
+    )?;
+    writeln!(text)?;
+
+    writeln!(text, "Inventory:")?;
+
+    let inventory = get_inventory_list(world, player);
+
+    if inventory.is_empty() {
+        writeln!(text, "  Nothing")?;
+    }
+
+    for (_, name) in inventory {
+        writeln!(text, "  {}", name)?;
+    }
+
+    writeln!(text)?;
+    writeln!(text, "Last messages:")?;
+
+    world
+        .resource::<GameLog>()?
+        .map(|log| -> std::fmt::Result {
+            let skip = log.entries.len().saturating_sub(MORGUE_LOG_LENGTH);
+
+            for entry in log.entries.iter().skip(skip) {
+                writeln!(text, "  {}", entry)?;
+            }
+
+            Ok(())
+        })??;
+
+    world
+        .resource::<TileMap>()?
+        .map(|map| -> std::fmt::Result {
+            writeln!(text)?;
+            writeln!(text, "Map of depth {}:", map.get_depth())?;
+
+            for y in 0..map.get_height() {
+                let row = (0..map.get_width())
+                    .map(|x| match map.get_tile(x, y) {
+                        _ if x == player_pos.x && y == player_pos.y => '@',
+                        Some(tile) if map.is_tile_revealed(x, y) => tile.ascii(),
+                        _ => ' ',
+                    })
+                    .collect::<String>();
+
+                writeln!(text, "{}", row.trim_end())?;
+            }
+
+            Ok(())
+        })??;
+
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_morgue_files_are_not_overwritten() {
+        let dir = std::env::temp_dir().join(format!("morgue-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let (first, _) = create_morgue_file(&dir, 1, 2).unwrap();
+        let (second, _) = create_morgue_file(&dir, 1, 2).unwrap();
+        let (other_seed, _) = create_morgue_file(&dir, 1, 3).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first.file_name().unwrap(), "morgue-1-2.txt");
+        assert_eq!(second.file_name().unwrap(), "morgue-1-2-2.txt");
+        assert_eq!(other_seed.file_name().unwrap(), "morgue-1-3.txt");
+    }
+}
@@ -0,0 +1,208 @@
+use crate::prelude::*;
+
+/// How long hit flashes are shown
+const FLASH_MS: f32 = 150.0;
+/// How long damage and healing numbers are shown
+const NUMBER_MS: f32 = 600.0;
+/// How long each tile of a spell trail lingers after the bolt passes
+const TRAIL_MS: f32 = 200.0;
+/// How long bursts, e.g. from shattering potions, are shown
+const BURST_MS: f32 = 300.0;
+
+/// Short-lived effect drawn on top of everything at `(x, y)`, e.g. a hit flash or a damage number.
+/// It appears after `delay_ms` and is removed `lifetime_ms` later.
+#[derive(Debug)]
+pub struct Particle {
+    pub x: i32,
+    pub y: i32,
+    pub glyph: FontCharType,
+    pub fg: RGB,
+    /// Drawn over the background of the tile if set
+    pub bg: Option<RGB>,
+    pub delay_ms: f32,
+    pub lifetime_ms: f32,
+}
+
+/// Particles being shown. Like projectile animations, they are purely visual and age with the
+/// frame time, so they live in this resource rather than as entities.
+#[derive(Debug, Default)]
+pub struct Particles(pub Vec<Particle>);
+
+/// Add particles to the `Particles` resource. Does nothing without it, e.g. in tests.
+fn add_particles<I>(world: &World, particles: I)
+where
+    I: IntoIterator<Item = Particle>,
+{
+    if let Ok(mut resource) = world.resource::<Particles>() {
+        let _ = resource.map(|mut resource| resource.0.extend(particles));
+    }
+}
+
+pub fn spawn_particle(
+    world: &World,
+    x: i32,
+    y: i32,
+    glyph: FontCharType,
+    fg: RGB,
+    bg: Option<RGB>,
+    lifetime_ms: f32,
+) {
+    add_particles(
+        world,
+        Some(Particle {
+            x,
+            y,
+            glyph,
+            fg,
+            bg,
+            delay_ms: 0.0,
+            lifetime_ms,
+        }),
+    );
+}
+
+/// Flash the background of `entity`'s tile, e.g. when it is hurt
+pub fn spawn_hit_flash(world: &World, config: &Config, entity: Entity) {
+    let flash = match world.query_one::<(&Position, &Renderable)>(entity) {
+        Ok(mut q) => q.get().map(|(pos, render)| (*pos, render.glyph, render.fg)),
+        Err(_) => None,
+    };
+
+    if let Some((pos, glyph, fg)) = flash {
+        spawn_particle(
+            world,
+            pos.x,
+            pos.y,
+            glyph,
+            fg,
+            Some(config.particle_hit.bg),
+            FLASH_MS,
+        );
+    }
+}
+
+/// Show `text` just above `entity`, e.g. the damage it took
+pub fn spawn_number(world: &World, entity: Entity, text: &str, fg: RGB) {
+    let pos = match world.get::<Position>(entity) {
+        Ok(pos) => *pos,
+        Err(_) => return,
+    };
+
+    for (i, c) in text.chars().enumerate() {
+        spawn_particle(
+            world,
+            pos.x + i as i32,
+            pos.y - 1,
+            to_cp437(c),
+            fg,
+            None,
+            NUMBER_MS,
+        );
+    }
+}
+
+/// Spray particles over every tile within `radius` of `(x, y)`, e.g. where a potion shatters
+pub fn spawn_burst(world: &World, x: i32, y: i32, radius: i32, fg: RGB) {
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            spawn_particle(world, x + dx, y + dy, to_cp437('*'), fg, None, BURST_MS);
+        }
+    }
+}
+
+/// Leave a fading trail behind a projectile flying along `path` at `ms_per_tile`
+pub fn spawn_trail(world: &World, path: &[Point], glyph: FontCharType, fg: RGB, ms_per_tile: f32) {
+    add_particles(
+        world,
+        path.iter().enumerate().map(|(i, point)| Particle {
+            x: point.x,
+            y: point.y,
+            glyph,
+            fg,
+            bg: None,
+            delay_ms: (i + 1) as f32 * ms_per_tile,
+            lifetime_ms: TRAIL_MS,
+        }),
+    );
+}
+
+/// Age particles by a frame, removing expired ones. Runs every frame rather than every turn, so
+/// particles never hold up the game.
+pub fn update_particles(world: &World, frame_time_ms: f32) {
+    if let Ok(mut particles) = world.resource::<Particles>() {
+        let _ = particles.map(|mut particles| {
+            for particle in particles.0.iter_mut() {
+                // Time left over from the delay counts towards the lifetime
+                particle.delay_ms -= frame_time_ms;
+
+                if particle.delay_ms < 0.0 {
+                    particle.lifetime_ms += particle.delay_ms;
+                    particle.delay_ms = 0.0;
+                }
+            }
+
+            particles.0.retain(|particle| particle.lifetime_ms > 0.0);
+        });
+    }
+}
+
+pub fn is_particle_shown(particle: &Particle) -> bool {
+    particle.delay_ms <= 0.0 && particle.lifetime_ms > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_particles() {
+        let mut world = World::new();
+        world.spawn_resource(Particles::default(), ()).unwrap();
+        let fg = RGB::from_f32(1.0, 1.0, 1.0);
+
+        spawn_particle(&world, 0, 0, to_cp437('!'), fg, None, 100.0);
+        spawn_trail(
+            &world,
+            &[Point::new(1, 0), Point::new(2, 0)],
+            to_cp437('*'),
+            fg,
+            50.0,
+        );
+
+        let shown = |world: &World| {
+            let mut shown = world
+                .query::<&Particles>()
+                .into_iter()
+                .flat_map(|(_, particles)| {
+                    particles
+                        .0
+                        .iter()
+                        .filter(|particle| is_particle_shown(particle))
+                        .map(|particle| particle.x)
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            shown.sort_unstable();
+            shown
+        };
+
+        assert_eq!(shown(&world), vec![0]);
+
+        update_particles(&world, 60.0);
+        assert_eq!(shown(&world), vec![0, 1]);
+
+        update_particles(&world, 60.0);
+        assert_eq!(shown(&world), vec![1, 2]);
+
+        update_particles(&world, TRAIL_MS);
+        let remaining = world
+            .query::<&Particles>()
+            .into_iter()
+            .map(|(_, particles)| particles.0.len())
+            .sum::<usize>();
+        assert_eq!(remaining, 0);
+
+        // Nothing but the resource itself is spawned
+        assert_eq!(world.iter().count(), 1);
+    }
+}
@@ -1,71 +1,297 @@
-use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auto_move, dialogue,
+    faction::{entity_reaction, Reaction},
+    identification::display_name,
+    inventory::{check_capacity, get_inventory_list},
+    look,
+    map_indexing_system::move_entity,
+    prelude::*,
+    shop::{self, ShopMode},
+    throwing::throw_target_error,
+};
+
+/// Everything the player can do which affects the game. Input is turned into these so it can be
+/// recorded and replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PlayerAction {
+    /// Move, attacking anything in the way
+    Move {
+        dx: i32,
+        dy: i32,
+    },
+    Run {
+        dx: i32,
+        dy: i32,
+    },
+    Wait,
+    PickUp,
+    Descend,
+    Explore,
+    Travel {
+        x: i32,
+        y: i32,
+    },
+    /// Take the next step of an ongoing run, travel or explore
+    ContinueAutoMove,
+    StopAutoMove,
+    /// Use the item at `index` in the player's inventory list
+    UseItem {
+        index: usize,
+    },
+    DropItem {
+        index: usize,
+    },
+    /// Throw the item at `index` in the player's inventory list at `(x, y)`
+    Throw {
+        index: usize,
+        x: i32,
+        y: i32,
+    },
+    /// Buy the item at `index` in the stock of the vendor at `(x, y)`
+    Buy {
+        x: i32,
+        y: i32,
+        index: usize,
+    },
+    /// Sell the item at `index` in the player's inventory list to the vendor at `(x, y)`
+    Sell {
+        x: i32,
+        y: i32,
+        index: usize,
+    },
+    /// Pick the available choice at `index` in the current dialogue
+    ChooseDialogue {
+        index: usize,
+    },
+}
+
+/// What the player asked for this frame
+pub enum PlayerInput {
+    Action(PlayerAction),
+    /// Open a menu or mode which doesn't affect the game by itself
+    RunState(RunState),
+    None,
+}
+
+/// Check for player input
+pub fn player_input(context: &mut BTerm, world: &World) -> anyhow::Result<PlayerInput> {
+    let is_auto_moving = match world.resource_entity::<Player>() {
+        Ok(player) => world.get::<AutoMove>(player).is_ok(),
+        Err(_) => false,
+    };
 
-/// Check for player input and try to move Player entity
-pub fn player_input(context: &mut BTerm, world: &mut World) -> anyhow::Result<RunState> {
     if let Some(key) = context.key {
-        match key {
-            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
-                try_player_move_and_attack(world, -1, 0)
+        if let Some((dx, dy)) = key_to_direction(key) {
+            return Ok(PlayerInput::Action(if context.shift {
+                PlayerAction::Run { dx, dy }
+            } else {
+                PlayerAction::Move { dx, dy }
+            }));
+        }
+
+        let input = match key {
+            VirtualKeyCode::Period if context.shift => PlayerInput::Action(PlayerAction::Descend),
+            VirtualKeyCode::Numpad5 | VirtualKeyCode::Period => {
+                PlayerInput::Action(PlayerAction::Wait)
             }
-            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
-                try_player_move_and_attack(world, 1, 0)
+            VirtualKeyCode::G => PlayerInput::Action(PlayerAction::PickUp),
+            VirtualKeyCode::O => PlayerInput::Action(PlayerAction::Explore),
+            VirtualKeyCode::I => PlayerInput::RunState(RunState::ShowInventory { page: 0 }),
+            VirtualKeyCode::D => PlayerInput::RunState(RunState::ShowDropMenu { page: 0 }),
+            VirtualKeyCode::T => PlayerInput::RunState(RunState::ShowThrowMenu { page: 0 }),
+            VirtualKeyCode::Minus => PlayerInput::RunState(RunState::ShowTravelMenu),
+            VirtualKeyCode::X => PlayerInput::RunState(look::start_look(world)?),
+            _ => PlayerInput::None,
+        };
+
+        // Any key stops automatic movement
+        Ok(match input {
+            PlayerInput::Action(_) => input,
+            _ if is_auto_moving => PlayerInput::Action(PlayerAction::StopAutoMove),
+            _ => input,
+        })
+    } else if context.left_click {
+        let (x, y) = context.mouse_pos();
+        Ok(PlayerInput::Action(PlayerAction::Travel { x, y }))
+    } else if is_auto_moving {
+        Ok(PlayerInput::Action(PlayerAction::ContinueAutoMove))
+    } else {
+        Ok(PlayerInput::None)
+    }
+}
+
+/// Perform a player action, returning the next `RunState`
+pub fn apply_player_action(world: &mut World, action: PlayerAction) -> anyhow::Result<RunState> {
+    if action != PlayerAction::ContinueAutoMove {
+        auto_move::cancel_auto_move(world);
+    }
+
+    match action {
+        PlayerAction::Move { dx, dy } => try_player_move_and_attack(world, dx, dy),
+        PlayerAction::Run { dx, dy } => auto_move::start_run(world, dx, dy),
+        PlayerAction::Wait => Ok(RunState::PlayerTurn),
+        PlayerAction::PickUp => try_pickup_item(world),
+        PlayerAction::Descend => try_descend(world),
+        PlayerAction::Explore => auto_move::start_explore(world),
+        PlayerAction::Travel { x, y } => auto_move::start_travel(world, x, y),
+        PlayerAction::ContinueAutoMove => {
+            Ok(auto_move::continue_auto_move(world)?.unwrap_or(RunState::AwaitingInput))
+        }
+        PlayerAction::StopAutoMove => Ok(RunState::AwaitingInput),
+        PlayerAction::UseItem { index } => {
+            let player = world.resource_entity::<Player>()?;
+
+            match get_inventory_list(world, player).get(index) {
+                Some((item, _)) => {
+                    world.spawn_command(UseItemCommand {
+                        user: player,
+                        item: *item,
+                    });
+                    Ok(RunState::PlayerTurn)
+                }
+                None => Ok(RunState::AwaitingInput),
             }
-            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
-                try_player_move_and_attack(world, 0, -1)
+        }
+        PlayerAction::DropItem { index } => {
+            let player = world.resource_entity::<Player>()?;
+
+            match get_inventory_list(world, player).get(index) {
+                Some((item, _)) => {
+                    world.spawn_command(DropItemCommand {
+                        dropper: player,
+                        item: *item,
+                    });
+                    Ok(RunState::PlayerTurn)
+                }
+                None => Ok(RunState::AwaitingInput),
             }
-            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
-                try_player_move_and_attack(world, 0, 1)
+        }
+        PlayerAction::Throw { index, x, y } => {
+            let player = world.resource_entity::<Player>()?;
+
+            if let Some(err) = throw_target_error(world, Position { x, y }) {
+                GameLog::resource_push(world, err)?;
+                return Ok(RunState::AwaitingInput);
             }
-            VirtualKeyCode::Numpad7 | VirtualKeyCode::Y => {
-                try_player_move_and_attack(world, -1, -1)
+
+            match get_inventory_list(world, player).get(index) {
+                Some((item, _)) => {
+                    world.spawn_command(ThrowItemCommand {
+                        thrower: player,
+                        item: *item,
+                        target: Position { x, y },
+                    });
+                    Ok(RunState::PlayerTurn)
+                }
+                None => Ok(RunState::AwaitingInput),
             }
-            VirtualKeyCode::Numpad9 | VirtualKeyCode::U => try_player_move_and_attack(world, 1, -1),
-            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => try_player_move_and_attack(world, -1, 1),
-            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => try_player_move_and_attack(world, 1, 1),
-            VirtualKeyCode::Numpad5 | VirtualKeyCode::Period => Ok(RunState::PlayerTurn),
-            VirtualKeyCode::G => try_pickup_item(world),
-            VirtualKeyCode::I => Ok(RunState::ShowInventory),
-            VirtualKeyCode::D => Ok(RunState::ShowDropMenu),
-            _ => Ok(RunState::AwaitingInput),
         }
-    } else {
-        Ok(RunState::AwaitingInput)
+        PlayerAction::Buy { x, y, index } => shop::trade(world, x, y, ShopMode::Buy, index),
+        PlayerAction::Sell { x, y, index } => shop::trade(world, x, y, ShopMode::Sell, index),
+        PlayerAction::ChooseDialogue { index } => match world.resource_clone::<RunState>()? {
+            RunState::Dialogue { speaker, node } => dialogue::choose(world, speaker, node, index),
+            _ => Ok(RunState::AwaitingInput),
+        },
+    }
+}
+
+pub fn key_to_direction(key: VirtualKeyCode) -> Option<(i32, i32)> {
+    match key {
+        VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => Some((-1, 0)),
+        VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => Some((1, 0)),
+        VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => Some((0, -1)),
+        VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => Some((0, 1)),
+        VirtualKeyCode::Numpad7 | VirtualKeyCode::Y => Some((-1, -1)),
+        VirtualKeyCode::Numpad9 | VirtualKeyCode::U => Some((1, -1)),
+        VirtualKeyCode::Numpad1 | VirtualKeyCode::B => Some((-1, 1)),
+        VirtualKeyCode::Numpad3 | VirtualKeyCode::N => Some((1, 1)),
+        _ => None,
     }
 }
 
-/// Move the player if possible
+/// Move the player if possible, attacking anything hostile or neutral in the way. Bumping a vendor
+/// opens their shop, and bumping a friendly NPC starts a dialogue with it or swaps places with it.
 fn try_player_move_and_attack(world: &mut World, dx: i32, dy: i32) -> anyhow::Result<RunState> {
+    try_player_step(world, dx, dy, true)
+}
+
+/// Move the player if possible, without attacking
+pub fn try_player_move(world: &mut World, dx: i32, dy: i32) -> anyhow::Result<RunState> {
+    try_player_step(world, dx, dy, false)
+}
+
+fn try_player_step(
+    world: &mut World,
+    dx: i32,
+    dy: i32,
+    allow_attack: bool,
+) -> anyhow::Result<RunState> {
     let mut is_taking_turn = false;
     let mut attack_cmd_bundle = None;
+    let mut shop_run_state = None;
+    let mut swap_with = None;
+    let mut move_to = None;
+    let mut talk_to = None;
+    let mut dialogue_run_state = None;
 
     if let Some((_, map)) = world.query::<&TileMap>().into_iter().next() {
         'outer: for (player_entity, (_player, pos, viewshed)) in world
-            .query::<(&Player, &mut Position, &mut Viewshed)>()
+            .query::<(&Player, &Position, &mut Viewshed)>()
             .into_iter()
         {
             let x = pos.x + dx;
             let y = pos.y + dy;
 
-            // TODO: Remove get_entities_on_tile call, use ECS query
-            for entity in map.get_entities_on_tile(x, y) {
-                match world.get::<CombatStats>(*entity) {
-                    Ok(_stats) => {
-                        attack_cmd_bundle = Some(InitiateAttackCommand {
-                            attacker: player_entity,
-                            defender: *entity,
+            if allow_attack {
+                // TODO: Remove get_entities_on_tile call, use ECS query
+                for entity in map.get_entities_on_tile(x, y) {
+                    if world.get::<Vendor>(*entity).is_ok() {
+                        shop_run_state = Some(RunState::ShowShop {
+                            x,
+                            y,
+                            mode: ShopMode::Buy,
                         });
-                        is_taking_turn = true;
 
-                        // TODO: Fix program flow
                         break 'outer;
                     }
-                    Err(_) => {}
+
+                    // Friendly NPCs talk if they have anything to say, otherwise make way if they
+                    // can move
+                    if entity_reaction(world, player_entity, *entity) == Reaction::Friendly {
+                        if let Some(run_state) = dialogue::start_dialogue(world, *entity) {
+                            dialogue_run_state = Some(run_state);
+                        } else if world.get::<Monster>(*entity).is_ok() {
+                            swap_with = Some((*entity, *pos));
+                            move_to = Some((player_entity, Position { x, y }));
+                            viewshed.dirty = true;
+                            is_taking_turn = true;
+                        } else {
+                            talk_to = Some(*entity);
+                        }
+
+                        break 'outer;
+                    }
+
+                    match world.get::<CombatStats>(*entity) {
+                        Ok(_stats) => {
+                            attack_cmd_bundle = Some(InitiateAttackCommand {
+                                attacker: player_entity,
+                                defender: *entity,
+                            });
+                            is_taking_turn = true;
+
+                            // TODO: Fix program flow
+                            break 'outer;
+                        }
+                        Err(_) => {}
+                    }
                 }
             }
 
             if map.get_tile(x, y).is_some() && !map.is_tile_blocked(x, y) {
-                pos.x = x;
-                pos.y = y;
+                move_to = Some((player_entity, Position { x, y }));
                 viewshed.dirty = true;
                 is_taking_turn = true;
             }
@@ -78,6 +304,30 @@ fn try_player_move_and_attack(world: &mut World, dx: i32, dy: i32) -> anyhow::Re
         world.spawn_command(components);
     }
 
+    if let Some(run_state) = shop_run_state.or(dialogue_run_state) {
+        return Ok(run_state);
+    }
+
+    if let Some((player_entity, to)) = move_to {
+        move_entity(world, player_entity, to)?;
+    }
+
+    if let Some((npc, player_old_pos)) = swap_with {
+        move_entity(world, npc, player_old_pos)?;
+
+        if let Ok(mut viewshed) = world.get_mut::<Viewshed>(npc) {
+            viewshed.dirty = true;
+        }
+
+        let name = world.get::<Name>(npc)?.to_string();
+        GameLog::resource_push(world, format!("You swap places with the {}.", name))?;
+    }
+
+    if let Some(npc) = talk_to {
+        let name = world.get::<Name>(npc)?.to_string();
+        GameLog::resource_push(world, format!("The {} has nothing to say.", name))?;
+    }
+
     Ok(if is_taking_turn {
         RunState::PlayerTurn
     } else {
@@ -101,6 +351,14 @@ fn try_pickup_item(world: &mut World) -> anyhow::Result<RunState> {
 
     match item_player_pair {
         Some((item_entity, player_entity)) => {
+            // Checked here too, so failing to pick something up doesn't take a turn
+            if let Err(err) = check_capacity(world, player_entity, item_entity) {
+                let name = display_name(world, item_entity).unwrap_or_default();
+                GameLog::resource_push(world, err.message(&name))?;
+
+                return Ok(RunState::AwaitingInput);
+            }
+
             world.spawn_command(PickupItemCommand {
                 collector: player_entity,
                 item: item_entity,
@@ -115,3 +373,21 @@ fn try_pickup_item(world: &mut World) -> anyhow::Result<RunState> {
         }
     }
 }
+
+fn try_descend(world: &mut World) -> anyhow::Result<RunState> {
+    let mut is_on_stairs = false;
+
+    if let Some((_, (_, pos))) = world.query::<(&Player, &Position)>().into_iter().next() {
+        if let Some((_, map)) = world.query::<&TileMap>().into_iter().next() {
+            is_on_stairs = map.is_tile_down_stairs(pos.x, pos.y);
+        }
+    }
+
+    if is_on_stairs {
+        Ok(RunState::NextLevel)
+    } else {
+        GameLog::resource_push(world, "There is no way down from here.")?;
+
+        Ok(RunState::AwaitingInput)
+    }
+}
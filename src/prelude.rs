@@ -4,6 +4,6 @@ pub use std::fmt::Display;
 pub use thiserror::Error;
 
 pub use crate::{
-    command::WorldCommands, components::*, config::Config, gamelog::GameLog,
+    bitgrid::BitGrid, command::WorldCommands, components::*, config::Config, gamelog::GameLog,
     resource::WorldResources, tilemap::TileMap, RunState,
 };
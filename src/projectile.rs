@@ -0,0 +1,173 @@
+use crate::{config::TileConfig, prelude::*};
+
+/// How long a projectile animation takes to cross each tile
+pub const MS_PER_TILE: f32 = 25.0;
+
+/// Something shown flying along `path`, which is removed once it reaches the end
+#[derive(Debug)]
+pub struct ProjectileAnimation {
+    pub path: Vec<Point>,
+    pub glyph: FontCharType,
+    pub fg: RGB,
+    pub elapsed_ms: f32,
+}
+
+/// Projectile animations in flight. They are purely visual and advance with the frame time, so
+/// they are kept in this one resource rather than spawned as entities, which would make entity ids
+/// depend on the frame rate and break replays.
+#[derive(Debug, Default)]
+pub struct ProjectileAnimations(pub Vec<ProjectileAnimation>);
+
+impl Projectile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Projectile::Arrow => "arrow",
+            Projectile::MagicBolt => "fire bolt",
+        }
+    }
+
+    pub fn tile(self, config: &Config) -> &TileConfig {
+        match self {
+            Projectile::Arrow => &config.arrow,
+            Projectile::MagicBolt => &config.magic_bolt,
+        }
+    }
+}
+
+/// Tiles something flying from `from` towards `target` passes through, stopping before the first
+/// tile which `is_wall`
+pub fn flight_path<F>(from: Point, target: Point, is_wall: F) -> Vec<Point>
+where
+    F: Fn(Point) -> bool,
+{
+    line2d(LineAlg::Bresenham, from, target)
+        .into_iter()
+        .skip_while(|point| *point == from)
+        .take_while(|point| !is_wall(*point))
+        .collect()
+}
+
+/// Tiles something flying from `from` towards `target` passes through up to where it comes down,
+/// and the creature it hits there, if any
+pub fn line_of_fire(
+    world: &World,
+    map: &TileMap,
+    from: Point,
+    target: Point,
+) -> (Vec<Point>, Option<Entity>) {
+    let mut path = Vec::new();
+
+    for point in flight_path(from, target, |p| !map.is_tile_walkable(p.x, p.y)) {
+        path.push(point);
+
+        let hit = map
+            .get_entities_on_tile(point.x, point.y)
+            .iter()
+            .copied()
+            .find(|entity| {
+                world.get::<BlocksTile>(*entity).is_ok()
+                    && world.get::<CombatStats>(*entity).is_ok()
+            });
+
+        if hit.is_some() {
+            return (path, hit);
+        }
+    }
+
+    (path, None)
+}
+
+/// Show something flying along `path`. Does nothing without the `ProjectileAnimations` resource,
+/// e.g. in tests.
+pub fn spawn_projectile_animation(world: &World, path: Vec<Point>, glyph: FontCharType, fg: RGB) {
+    if path.is_empty() {
+        return;
+    }
+
+    if let Ok(mut animations) = world.resource::<ProjectileAnimations>() {
+        let _ = animations.map(|mut animations| {
+            animations.0.push(ProjectileAnimation {
+                path,
+                glyph,
+                fg,
+                elapsed_ms: 0.0,
+            })
+        });
+    }
+}
+
+/// Advance projectile animations by a frame, removing finished ones. Runs every frame rather than
+/// every turn.
+pub fn animate_projectiles(world: &World, frame_time_ms: f32) {
+    if let Ok(mut animations) = world.resource::<ProjectileAnimations>() {
+        let _ = animations.map(|mut animations| {
+            for animation in animations.0.iter_mut() {
+                animation.elapsed_ms += frame_time_ms;
+            }
+
+            animations
+                .0
+                .retain(|animation| animation_tile(animation).is_some());
+        });
+    }
+}
+
+/// The tile a projectile animation is currently on, or `None` once it has finished
+pub fn animation_tile(animation: &ProjectileAnimation) -> Option<Point> {
+    animation
+        .path
+        .get((animation.elapsed_ms / MS_PER_TILE) as usize)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flight_path_stops_at_walls() {
+        let from = Point::new(0, 0);
+
+        assert_eq!(
+            flight_path(from, Point::new(3, 0), |_| false),
+            vec![Point::new(1, 0), Point::new(2, 0), Point::new(3, 0)]
+        );
+        assert_eq!(
+            flight_path(from, Point::new(3, 3), |p| p.x == 2),
+            vec![Point::new(1, 1)]
+        );
+        assert!(flight_path(from, from, |_| false).is_empty());
+    }
+
+    #[test]
+    fn test_animate_projectiles() {
+        let mut world = World::new();
+        world
+            .spawn_resource(ProjectileAnimations::default(), ())
+            .unwrap();
+
+        let animations = |world: &World| {
+            world
+                .query::<&ProjectileAnimations>()
+                .into_iter()
+                .flat_map(|(_, animations)| animations.0.iter().map(animation_tile))
+                .collect::<Vec<_>>()
+        };
+
+        spawn_projectile_animation(
+            &world,
+            vec![Point::new(1, 0), Point::new(2, 0)],
+            to_cp437('*'),
+            RGB::from_f32(1.0, 1.0, 1.0),
+        );
+
+        animate_projectiles(&world, MS_PER_TILE * 1.5);
+        assert_eq!(animations(&world), vec![Some(Point::new(2, 0))]);
+
+        animate_projectiles(&world, MS_PER_TILE);
+        assert!(animations(&world).is_empty());
+
+        // Nothing but the resource itself is spawned
+        assert_eq!(world.iter().count(), 1);
+    }
+}
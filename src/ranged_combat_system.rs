@@ -0,0 +1,129 @@
+use bracket_lib::random::RandomNumberGenerator;
+
+use crate::{
+    combat_rules::{roll_power_attack, AttackResult},
+    equipment::equipment_bonus,
+    particle::spawn_trail,
+    prelude::*,
+    projectile::{line_of_fire, spawn_projectile_animation, MS_PER_TILE},
+};
+
+pub fn ranged_combat_system(world: &mut World, config: &Config) -> anyhow::Result<()> {
+    let mut damage_commands_batch = Vec::new();
+    let mut animations = Vec::new();
+
+    let player_entity = world.resource_entity::<Player>().ok();
+
+    let attacks = world
+        .query::<&InitiateRangedAttackCommand>()
+        .into_iter()
+        .map(|(_, cmd)| (cmd.attacker, cmd.defender))
+        .collect::<Vec<_>>();
+
+    for (attacker, defender) in attacks {
+        let attacker_data = world
+            .query_one::<(&CombatStats, &Name, &Position, &RangedAttack)>(attacker)
+            .ok()
+            .and_then(|mut q| {
+                q.get()
+                    .map(|(stats, name, pos, ranged)| (stats.hp, name.0.clone(), *pos, *ranged))
+            });
+        let (attacker_hp, attacker_name, attacker_pos, ranged) = match attacker_data {
+            Some(data) => data,
+            None => {
+                console::log(format!(
+                    "Error: InitiateRangedAttackCommand attacker Entity {} can't attack from range",
+                    attacker.id()
+                ));
+                continue;
+            }
+        };
+
+        let target_pos = match world.get::<Position>(defender) {
+            Ok(pos) => *pos,
+            Err(_) => continue,
+        };
+
+        if attacker_hp <= 0 {
+            continue;
+        }
+
+        // The shot hits whatever is first in the line of fire, which may not be the defender
+        let (path, hit) = world
+            .resource::<TileMap>()?
+            .map(|map| line_of_fire(world, &map, attacker_pos.to_point(), target_pos.into()))?;
+
+        let tile = ranged.projectile.tile(config);
+        animations.push((ranged.projectile, path, tile.glyph, tile.fg));
+
+        let shot = if Some(attacker) == player_entity {
+            format!("Your {}", ranged.projectile.name())
+        } else {
+            format!("{}'s {}", attacker_name, ranged.projectile.name())
+        };
+
+        let hit = match hit {
+            Some(hit) => hit,
+            None => {
+                GameLog::resource_push_combat(world, format!("{} misses.", shot))?;
+                continue;
+            }
+        };
+
+        let (defense, hit_name) = match world.query_one::<(&CombatStats, &Name)>(hit) {
+            Ok(mut q) => match q.get() {
+                Some((stats, name)) => (stats.defense, name.0.clone()),
+                None => continue,
+            },
+            Err(_) => continue,
+        };
+
+        let defense = defense + equipment_bonus(world, hit).defense;
+        let result = world
+            .resource::<RandomNumberGenerator>()?
+            .map(|mut rng| roll_power_attack(&mut rng, ranged.power, defense))?;
+
+        let hit_name = if Some(hit) == player_entity {
+            "you".to_string()
+        } else {
+            hit_name
+        };
+
+        let (message, damage) = match result {
+            AttackResult::Fumble => (format!("{} goes wide.", shot), 0),
+            AttackResult::Miss => (format!("{} misses {}.", shot, hit_name), 0),
+            AttackResult::Hit { damage } => (
+                format!("{} hits {} for {} hp.", shot, hit_name, damage),
+                damage,
+            ),
+            AttackResult::Critical { damage } => (
+                format!("{} critically hits {} for {} hp!", shot, hit_name, damage),
+                damage,
+            ),
+        };
+
+        GameLog::resource_push_combat(world, message)?;
+
+        if damage > 0 {
+            damage_commands_batch.push(DamageCommand {
+                entity: hit,
+                amount: damage,
+                damage_type: ranged.damage_type,
+                source: Some(attacker),
+            })
+        }
+    }
+
+    for (projectile, path, glyph, fg) in animations {
+        // Magic leaves a trail behind it
+        if projectile == Projectile::MagicBolt {
+            spawn_trail(world, &path, to_cp437('∙'), fg, MS_PER_TILE);
+        }
+
+        spawn_projectile_animation(world, path, glyph, fg);
+    }
+
+    world.spawn_batch_commands(damage_commands_batch);
+
+    Ok(())
+}
@@ -0,0 +1,344 @@
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{particle, player::PlayerAction, prelude::*, projectile, State};
+
+/// Version of the replay file format written by this build.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// First line of a replay file. Every following line is a `ReplayStep`.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReplayHeader {
+    version: u32,
+    seed: u64,
+}
+
+/// A recorded action, with a hash of the game state just before it was performed, so a replay can
+/// report exactly where it diverged from the original game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReplayStep {
+    state_hash: u64,
+    action: PlayerAction,
+}
+
+/// Writes the player's actions to a replay file as they are performed
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+}
+
+impl ReplayRecorder {
+    pub fn create(path: &Path, seed: u64) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Could not create replay '{}'", path.to_string_lossy()))?;
+
+        let mut recorder = Self {
+            writer: BufWriter::new(file),
+        };
+
+        recorder.write_line(&ReplayHeader {
+            version: REPLAY_VERSION,
+            seed,
+        })?;
+
+        Ok(recorder)
+    }
+
+    pub fn record(&mut self, world: &World, action: PlayerAction) -> anyhow::Result<()> {
+        self.write_line(&ReplayStep {
+            state_hash: state_hash(world),
+            action,
+        })
+    }
+
+    /// Lines are flushed immediately, so the replay survives a crash
+    fn write_line<T: Serialize>(&mut self, value: &T) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// A loaded replay file
+pub struct Replay {
+    seed: u64,
+    steps: VecDeque<ReplayStep>,
+    steps_taken: usize,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let replay_string = fs::read_to_string(path)
+            .with_context(|| format!("Could not read replay '{}'", path.to_string_lossy()))?;
+
+        Self::from_str(&replay_string)
+    }
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let header: ReplayHeader = match lines.next() {
+            Some((_, line)) => serde_json::from_str(line).context("Error parsing replay header")?,
+            None => return Err(anyhow!("Replay is empty")),
+        };
+
+        if header.version != REPLAY_VERSION {
+            return Err(anyhow!(
+                "Unsupported replay version {} (supported version: {})",
+                header.version,
+                REPLAY_VERSION
+            ));
+        }
+
+        let steps = lines
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("Error parsing replay line {}", i + 1))
+            })
+            .collect::<anyhow::Result<VecDeque<ReplayStep>>>()?;
+
+        Ok(Self {
+            seed: header.seed,
+            steps,
+            steps_taken: 0,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn steps_taken(&self) -> usize {
+        self.steps_taken
+    }
+
+    /// The next recorded action, or `None` when the replay is finished. Fails if the game is not in
+    /// the state it was recorded in.
+    fn next_action(&mut self, world: &World) -> anyhow::Result<Option<PlayerAction>> {
+        let step = match self.steps.pop_front() {
+            Some(step) => step,
+            None => return Ok(None),
+        };
+
+        let current_hash = state_hash(world);
+
+        if current_hash != step.state_hash {
+            return Err(anyhow!(
+                "Replay diverged before action {} ({:?}): expected state hash {:016x}, got {:016x}",
+                self.steps_taken + 1,
+                step.action,
+                step.state_hash,
+                current_hash
+            ));
+        }
+
+        self.steps_taken += 1;
+
+        Ok(Some(step.action))
+    }
+}
+
+pub enum PlaybackEvent {
+    Action(PlayerAction),
+    Waiting,
+    Finished,
+}
+
+/// Plays a replay back in the window, at a speed which can be changed with + and -. Space pauses
+/// and Escape hands control back to the player.
+pub struct ReplayPlayback {
+    replay: Replay,
+    actions_per_second: f32,
+    elapsed_ms: f32,
+    is_paused: bool,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay, actions_per_second: f32) -> Self {
+        Self {
+            replay,
+            actions_per_second,
+            elapsed_ms: 0.0,
+            is_paused: false,
+        }
+    }
+
+    pub fn next_event(&mut self, context: &BTerm, world: &World) -> anyhow::Result<PlaybackEvent> {
+        match context.key {
+            Some(VirtualKeyCode::Escape) => return Ok(PlaybackEvent::Finished),
+            Some(VirtualKeyCode::Space) => self.is_paused = !self.is_paused,
+            Some(VirtualKeyCode::Equals) | Some(VirtualKeyCode::NumpadAdd) => {
+                self.actions_per_second = (self.actions_per_second * 2.0).min(1000.0);
+            }
+            Some(VirtualKeyCode::Minus) | Some(VirtualKeyCode::NumpadSubtract) => {
+                self.actions_per_second = (self.actions_per_second / 2.0).max(0.5);
+            }
+            _ => {}
+        }
+
+        if self.is_paused {
+            return Ok(PlaybackEvent::Waiting);
+        }
+
+        self.elapsed_ms += context.frame_time_ms;
+
+        if self.elapsed_ms < 1000.0 / self.actions_per_second {
+            return Ok(PlaybackEvent::Waiting);
+        }
+
+        self.elapsed_ms = 0.0;
+
+        Ok(match self.replay.next_action(world)? {
+            Some(action) => PlaybackEvent::Action(action),
+            None => PlaybackEvent::Finished,
+        })
+    }
+}
+
+/// Run a replay to the end without a window, returning the number of actions replayed and the
+/// final state hash
+pub fn run_headless(state: &mut State, mut replay: Replay) -> anyhow::Result<(usize, u64)> {
+    loop {
+        let run_state = state.world.resource_clone::<RunState>()?;
+
+        let next_run_state = match run_state {
+            RunState::GameOver => break,
+            _ if run_state.is_waiting_for_player() => match replay.next_action(&state.world)? {
+                Some(action) => state.perform_action(action),
+                None => break,
+            },
+            _ => {
+                let next_run_state = state.advance(run_state);
+
+                if next_run_state == run_state {
+                    return Err(anyhow!("Replay got stuck in {:?}", run_state));
+                }
+
+                next_run_state
+            }
+        };
+
+        state
+            .world
+            .resource::<RunState>()?
+            .map(|mut rs| *rs = next_run_state)?;
+
+        // Nothing is drawn, so finish effects straight away rather than let them pile up
+        projectile::animate_projectiles(&state.world, f32::INFINITY);
+        particle::update_particles(&state.world, f32::INFINITY);
+    }
+
+    Ok((replay.steps_taken(), state_hash(&state.world)))
+}
+
+/// A hash of everything a replay should reproduce: the map, and the name, position, health and
+/// owner of every named entity, plus the game log
+fn state_hash(world: &World) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    if let Some((_, map)) = world.query::<&TileMap>().into_iter().next() {
+        map.get_depth().hash(&mut hasher);
+
+        for y in 0..map.get_height() {
+            for x in 0..map.get_width() {
+                map.get_tile(x, y).hash(&mut hasher);
+                map.is_tile_revealed(x, y).hash(&mut hasher);
+            }
+        }
+    }
+
+    let mut entities = world
+        .query::<(
+            &Name,
+            Option<&Position>,
+            Option<&CombatStats>,
+            Option<&InInventory>,
+        )>()
+        .into_iter()
+        .map(|(entity, (name, pos, stats, in_inventory))| {
+            (
+                entity.to_bits(),
+                name.to_string(),
+                pos.copied(),
+                stats.map(|stats| (stats.hp, stats.max_hp)),
+                in_inventory.map(|in_inventory| in_inventory.owner.to_bits()),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    entities.sort_by_key(|(bits, ..)| *bits);
+    entities.hash(&mut hasher);
+
+    if let Some((_, log)) = world.query::<&GameLog>().into_iter().next() {
+        log.entries.hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_roundtrip() {
+        let steps = vec![
+            ReplayStep {
+                state_hash: 1,
+                action: PlayerAction::Move { dx: 1, dy: 0 },
+            },
+            ReplayStep {
+                state_hash: u64::MAX,
+                action: PlayerAction::UseItem { index: 2 },
+            },
+        ];
+
+        let mut s = serde_json::to_string(&ReplayHeader {
+            version: REPLAY_VERSION,
+            seed: 42,
+        })
+        .unwrap();
+
+        for step in steps.iter() {
+            s.push('\n');
+            s.push_str(&serde_json::to_string(step).unwrap());
+        }
+
+        let replay = Replay::from_str(&s).unwrap();
+
+        assert_eq!(replay.seed(), 42);
+        assert_eq!(
+            replay
+                .steps
+                .iter()
+                .map(|step| (step.state_hash, step.action))
+                .collect::<Vec<_>>(),
+            steps
+                .iter()
+                .map(|step| (step.state_hash, step.action))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_replay_rejects_other_versions() {
+        let s = serde_json::to_string(&ReplayHeader {
+            version: REPLAY_VERSION + 1,
+            seed: 42,
+        })
+        .unwrap();
+
+        assert!(Replay::from_str(&s).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// Directory name inside the user's data directory
+const DATA_DIR_NAME: &str = "rusty-hecs-roguelike";
+const SCORES_FILE_NAME: &str = "scores";
+
+/// Where scores and morgue files are kept. Falls back to the current directory if the platform has
+/// no data directory.
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(DATA_DIR_NAME))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn scores_path() -> PathBuf {
+    data_dir().join(SCORES_FILE_NAME)
+}
+
+/// One finished run. The scores file has one of these per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub timestamp: u64,
+    pub seed: u64,
+    pub depth: i32,
+    #[serde(default)]
+    pub xp: u32,
+    pub turns: u32,
+    pub kills: u32,
+    pub cause_of_death: Option<String>,
+}
+
+pub fn append_score(entry: &ScoreEntry) -> anyhow::Result<()> {
+    fs::create_dir_all(data_dir())?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(scores_path())?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+
+    Ok(())
+}
+
+/// All recorded runs, in the order they were played. No scores file means no runs yet.
+pub fn load_scores() -> anyhow::Result<Vec<ScoreEntry>> {
+    let path = scores_path();
+
+    match fs::read_to_string(&path) {
+        Ok(s) => scores_from_str(&s),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => {
+            Err(err).with_context(|| format!("Could not read scores '{}'", path.to_string_lossy()))
+        }
+    }
+}
+
+fn scores_from_str(s: &str) -> anyhow::Result<Vec<ScoreEntry>> {
+    s.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Error parsing score line {}", i + 1))
+        })
+        .collect()
+}
+
+/// Column the score table is sorted by. Every order puts the best or newest runs first.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScoreSort {
+    Depth,
+    Xp,
+    Turns,
+    Recent,
+}
+
+impl ScoreSort {
+    pub fn label(self) -> &'static str {
+        match self {
+            ScoreSort::Depth => "depth",
+            ScoreSort::Xp => "XP",
+            ScoreSort::Turns => "turns",
+            ScoreSort::Recent => "most recent",
+        }
+    }
+
+    pub fn sort(self, scores: &mut [ScoreEntry]) {
+        match self {
+            ScoreSort::Depth => scores.sort_by(|a, b| {
+                (b.depth, b.xp)
+                    .cmp(&(a.depth, a.xp))
+                    .then(a.turns.cmp(&b.turns))
+            }),
+            ScoreSort::Xp => scores.sort_by_key(|entry| Reverse((entry.xp, entry.depth))),
+            ScoreSort::Turns => scores.sort_by_key(|entry| Reverse(entry.turns)),
+            ScoreSort::Recent => scores.sort_by_key(|entry| Reverse(entry.timestamp)),
+        }
+    }
+}
+
+impl FromStr for ScoreSort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "depth" => Ok(ScoreSort::Depth),
+            "xp" => Ok(ScoreSort::Xp),
+            "turns" => Ok(ScoreSort::Turns),
+            "recent" => Ok(ScoreSort::Recent),
+            _ => Err(anyhow!(
+                "Unknown score sort '{}' (expected depth, xp, turns or recent)",
+                s
+            )),
+        }
+    }
+}
+
+pub fn score_table_header() -> String {
+    format!(
+        "{:>4} {:>5} {:>6} {:>5}  {}",
+        "#", "Depth", "XP", "Turns", "Cause of death"
+    )
+}
+
+/// Rows of the score table, ranked in the given order
+pub fn score_table_rows(scores: &[ScoreEntry]) -> Vec<String> {
+    scores
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            format!(
+                "{:>4} {:>5} {:>6} {:>5}  {}",
+                i + 1,
+                entry.depth,
+                entry.xp,
+                entry.turns,
+                entry.cause_of_death.as_deref().unwrap_or("Still alive")
+            )
+        })
+        .collect()
+}
+
+/// Print the score table to stdout, for `--scores`
+pub fn print_scores(sort: ScoreSort) -> anyhow::Result<()> {
+    let mut scores = load_scores()?;
+
+    if scores.is_empty() {
+        println!("No scores yet.");
+        return Ok(());
+    }
+
+    sort.sort(&mut scores);
+
+    println!("{}", score_table_header());
+    for row in score_table_rows(&scores) {
+        println!("{}", row);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: u64, depth: i32, xp: u32, turns: u32) -> ScoreEntry {
+        ScoreEntry {
+            timestamp,
+            seed: 0,
+            depth,
+            xp,
+            turns,
+            kills: 0,
+            cause_of_death: None,
+        }
+    }
+
+    #[test]
+    fn test_score_sort() {
+        let mut scores = vec![
+            entry(1, 2, 30, 500),
+            entry(2, 3, 10, 900),
+            entry(3, 3, 20, 100),
+        ];
+
+        ScoreSort::Depth.sort(&mut scores);
+        assert_eq!(
+            scores.iter().map(|e| e.timestamp).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+
+        ScoreSort::Xp.sort(&mut scores);
+        assert_eq!(
+            scores.iter().map(|e| e.timestamp).collect::<Vec<_>>(),
+            vec![1, 3, 2]
+        );
+
+        ScoreSort::Turns.sort(&mut scores);
+        assert_eq!(
+            scores.iter().map(|e| e.timestamp).collect::<Vec<_>>(),
+            vec![2, 1, 3]
+        );
+
+        ScoreSort::Recent.sort(&mut scores);
+        assert_eq!(
+            scores.iter().map(|e| e.timestamp).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn test_scores_from_str_accepts_entries_without_xp() {
+        let s = r#"{"timestamp":1,"seed":2,"depth":3,"turns":4,"kills":5,"cause_of_death":"Killed by Orc"}"#;
+
+        let scores = scores_from_str(s).unwrap();
+
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].xp, 0);
+        assert_eq!(scores[0].cause_of_death.as_deref(), Some("Killed by Orc"));
+        assert!(scores_from_str("not json").is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::anyhow;
+
+use crate::{
+    equipment::try_unequip,
+    inventory::{add_to_inventory, check_capacity, get_inventory_list, item_quantity},
+    prelude::*,
+};
+
+/// Vendors buy items back for this fraction of what they sell them for
+const SELL_PRICE_DIVISOR: i32 = 2;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ShopMode {
+    Buy,
+    Sell,
+}
+
+impl ShopMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            ShopMode::Buy => ShopMode::Sell,
+            ShopMode::Sell => ShopMode::Buy,
+        }
+    }
+}
+
+/// An item listed in the shop menu. `price` is `None` if the item can't be traded. Stacks are traded
+/// whole, for the price of every item in them.
+pub struct ShopListing {
+    pub item: Entity,
+    pub name: String,
+    pub price: Option<i32>,
+}
+
+pub fn vendor_at(world: &World, x: i32, y: i32) -> Option<Entity> {
+    world
+        .query::<(&Vendor, &Position)>()
+        .into_iter()
+        .find(|(_, (_, pos))| pos.x == x && pos.y == y)
+        .map(|(entity, _)| entity)
+}
+
+/// The vendor's stock when buying, or the player's inventory when selling
+pub fn shop_listings(world: &World, vendor: Entity, mode: ShopMode) -> Vec<ShopListing> {
+    let owner = match mode {
+        ShopMode::Buy => vendor,
+        ShopMode::Sell => match world.resource_entity::<Player>() {
+            Ok(player) => player,
+            Err(_) => return Vec::new(),
+        },
+    };
+
+    get_inventory_list(world, owner)
+        .into_iter()
+        .map(|(item, name)| {
+            let quantity = item_quantity(world, item);
+            let price = world.get::<Value>(item).ok().map(|value| match mode {
+                ShopMode::Buy => value.gold * quantity,
+                ShopMode::Sell => (value.gold / SELL_PRICE_DIVISOR).max(1) * quantity,
+            });
+
+            ShopListing { item, name, price }
+        })
+        .collect()
+}
+
+/// Buy or sell the listing at `index` with the vendor at `(x, y)`. Trading doesn't take a turn, so
+/// the shop stays open afterwards.
+pub fn trade(
+    world: &mut World,
+    x: i32,
+    y: i32,
+    mode: ShopMode,
+    index: usize,
+) -> anyhow::Result<RunState> {
+    let vendor = match vendor_at(world, x, y) {
+        Some(vendor) => vendor,
+        None => return Ok(RunState::AwaitingInput),
+    };
+
+    let player = world.resource_entity::<Player>()?;
+    let vendor_name = world.get::<Name>(vendor)?.to_string();
+
+    let listing = match shop_listings(world, vendor, mode).into_iter().nth(index) {
+        Some(listing) => listing,
+        None => return Ok(RunState::ShowShop { x, y, mode }),
+    };
+
+    let price = match listing.price {
+        Some(price) => price,
+        None => {
+            GameLog::resource_push(
+                world,
+                format!("The {} has no use for the {}.", vendor_name, listing.name),
+            )?;
+            return Ok(RunState::ShowShop { x, y, mode });
+        }
+    };
+
+    let gold = world
+        .get::<Gold>(player)
+        .map(|gold| gold.amount)
+        .unwrap_or(0);
+
+    match mode {
+        ShopMode::Buy => {
+            if gold < price {
+                GameLog::resource_push(world, format!("You can't afford the {}.", listing.name))?;
+                return Ok(RunState::ShowShop { x, y, mode });
+            }
+
+            if let Err(err) = check_capacity(world, player, listing.item) {
+                GameLog::resource_push(world, err.message(&listing.name))?;
+                return Ok(RunState::ShowShop { x, y, mode });
+            }
+
+            add_to_inventory(world, listing.item, player)?;
+            world.insert_one(
+                player,
+                Gold {
+                    amount: gold - price,
+                },
+            )?;
+
+            GameLog::resource_push(
+                world,
+                format!("You buy the {} for {} gold.", listing.name, price),
+            )?;
+        }
+        ShopMode::Sell => {
+            if !try_unequip(world, listing.item)? {
+                return Ok(RunState::ShowShop { x, y, mode });
+            }
+
+            world
+                .remove_one::<InInventory>(listing.item)
+                .map_err(|_| anyhow!("Item {} is not in an inventory", listing.item.id()))?;
+
+            // Sold items go on sale, so they can be bought back
+            add_to_inventory(world, listing.item, vendor)?;
+            world.insert_one(
+                player,
+                Gold {
+                    amount: gold + price,
+                },
+            )?;
+
+            GameLog::resource_push(
+                world,
+                format!("You sell the {} for {} gold.", listing.name, price),
+            )?;
+        }
+    }
+
+    Ok(RunState::ShowShop { x, y, mode })
+}
@@ -1,6 +1,6 @@
 use anyhow::anyhow;
 
-use crate::prelude::*;
+use crate::{inventory::add_to_inventory, prelude::*};
 
 const MAX_MONSTERS: i32 = 4;
 const MAX_ITEMS: i32 = 2;
@@ -10,6 +10,42 @@ const SPAWN_ATTEMPTS_TIMEOUT: i32 = 1000;
 const RENDER_ORDER_PLAYER: i32 = 0;
 const RENDER_ORDER_MONSTER: i32 = -1;
 const RENDER_ORDER_ITEM: i32 = -2;
+const RENDER_ORDER_FEATURE: i32 = -3;
+const RENDER_ORDER_CORPSE: i32 = -4;
+const RENDER_ORDER_DECAL: i32 = -5;
+
+const CORPSE_DECAY_TURNS: i32 = 150;
+const BLOOD_DECAY_TURNS: i32 = 400;
+
+const PLAYER_INVENTORY_SLOTS: usize = 40;
+const PLAYER_MAX_WEIGHT: f32 = 30.0;
+
+/// Chance out of 6 for a room to have a brazier
+const BRAZIER_CHANCE: i32 = 1;
+
+/// Chance out of 6 for a room to have a pile of gold
+const GOLD_CHANCE: i32 = 2;
+
+/// Chance out of 6 for a level to have a vendor
+const VENDOR_CHANCE: i32 = 3;
+const MAX_VENDOR_STOCK: i32 = 5;
+
+/// Chance out of 6 for a level to have a villager
+const VILLAGER_CHANCE: i32 = 2;
+
+/// Chance out of 6 for a monster to carry an item, which it may use
+const MONSTER_ITEM_CHANCE: i32 = 2;
+
+/// Chances out of 6 for equipment to be cursed, or to have a bonus
+const CURSED_CHANCE: i32 = 1;
+const BLESSED_CHANCE: i32 = 1;
+
+fn torch_light() -> LightSource {
+    LightSource {
+        radius: 5,
+        color: RGB::from_f32(1.0, 0.85, 0.6),
+    }
+}
 
 pub fn player(world: &mut World, config: &Config, x: i32, y: i32) -> anyhow::Result<Entity> {
     Ok(world.spawn_resource(
@@ -20,7 +56,14 @@ pub fn player(world: &mut World, config: &Config, x: i32, y: i32) -> anyhow::Res
             config
                 .player
                 .to_renderable_with_render_order(RENDER_ORDER_PLAYER),
-            Viewshed::with_range(8),
+            Viewshed::with_range(16),
+            torch_light(),
+            Gold { amount: 0 },
+            InventoryCapacity {
+                max_slots: PLAYER_INVENTORY_SLOTS,
+                max_weight: PLAYER_MAX_WEIGHT,
+            },
+            Faction::Player,
             CombatStats {
                 max_hp: 30,
                 hp: 30,
@@ -34,18 +77,29 @@ pub fn player(world: &mut World, config: &Config, x: i32, y: i32) -> anyhow::Res
 pub fn rng_monster(world: &mut World, config: &Config, x: i32, y: i32) -> anyhow::Result<Entity> {
     let dice_roll = world
         .resource::<RandomNumberGenerator>()?
-        .map(|mut rng| rng.roll_dice(1, 2))?;
+        .map(|mut rng| rng.roll_dice(1, 6))?;
 
     let entity = match dice_roll {
-        1 => orc(world, config, x, y),
-        _ => goblin(world, config, x, y),
+        1 | 2 => orc(world, config, x, y),
+        3 | 4 => goblin(world, config, x, y),
+        5 => goblin_archer(world, config, x, y),
+        _ => orc_shaman(world, config, x, y),
     };
 
+    let carries_item = world
+        .resource::<RandomNumberGenerator>()?
+        .map(|mut rng| rng.roll_dice(1, 6) <= MONSTER_ITEM_CHANCE)?;
+
+    if carries_item {
+        let item = rng_item(world, config, x, y)?;
+        add_to_inventory(world, item, entity)?;
+    }
+
     Ok(entity)
 }
 
 fn orc(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
-    monster(
+    let entity = monster(
         world,
         x,
         y,
@@ -53,11 +107,24 @@ fn orc(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
             .orc
             .to_renderable_with_render_order(RENDER_ORDER_MONSTER),
         "Orc",
-    )
+        Faction::Orcs,
+    );
+
+    // Orcs hunt in the dark, and are hardened to poison and cold
+    let _ = world.insert(
+        entity,
+        (
+            Darkvision,
+            LootTable::Orc,
+            Resistant(vec![DamageType::Poison, DamageType::Cold]),
+        ),
+    );
+
+    entity
 }
 
 fn goblin(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
-    monster(
+    let entity = monster(
         world,
         x,
         y,
@@ -65,7 +132,135 @@ fn goblin(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
             .goblin
             .to_renderable_with_render_order(RENDER_ORDER_MONSTER),
         "Goblin",
-    )
+        Faction::Goblins,
+    );
+
+    // Goblins can't see in the dark, so carry a dim torch which gives them away
+    let _ = world.insert_one(
+        entity,
+        LightSource {
+            radius: 3,
+            color: RGB::from_f32(0.8, 0.5, 0.3),
+        },
+    );
+    let _ = world.insert_one(entity, LootTable::Goblin);
+    let _ = world.insert_one(entity, Vulnerable(vec![DamageType::Fire]));
+
+    entity
+}
+
+/// Keeps its distance and shoots poisoned arrows
+fn goblin_archer(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
+    let entity = monster(
+        world,
+        x,
+        y,
+        config
+            .goblin_archer
+            .to_renderable_with_render_order(RENDER_ORDER_MONSTER),
+        "Goblin archer",
+        Faction::Goblins,
+    );
+
+    let _ = world.insert(
+        entity,
+        (
+            CombatStats {
+                max_hp: 10,
+                hp: 10,
+                defense: 0,
+                power: 2,
+            },
+            RangedAttack {
+                range: 6.0,
+                min_distance: 3.0,
+                power: 4,
+                projectile: Projectile::Arrow,
+                damage_type: DamageType::Physical,
+            },
+            PoisonsOnHit {
+                turns: 4,
+                damage: 1,
+            },
+            LightSource {
+                radius: 3,
+                color: RGB::from_f32(0.8, 0.5, 0.3),
+            },
+            LootTable::Goblin,
+            Vulnerable(vec![DamageType::Fire]),
+        ),
+    );
+
+    entity
+}
+
+/// Hangs back behind other orcs and hurls bolts of fire
+fn orc_shaman(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
+    let entity = monster(
+        world,
+        x,
+        y,
+        config
+            .orc_shaman
+            .to_renderable_with_render_order(RENDER_ORDER_MONSTER),
+        "Orc shaman",
+        Faction::Orcs,
+    );
+
+    let _ = world.insert(
+        entity,
+        (
+            CombatStats {
+                max_hp: 12,
+                hp: 12,
+                defense: 0,
+                power: 2,
+            },
+            RangedAttack {
+                range: 5.0,
+                min_distance: 2.0,
+                power: 6,
+                projectile: Projectile::MagicBolt,
+                damage_type: DamageType::Fire,
+            },
+            Darkvision,
+            LootTable::Orc,
+            // Its own fire can't hurt it, but it has none of the toughness of other orcs
+            Immune(vec![DamageType::Fire]),
+            Vulnerable(vec![DamageType::Cold]),
+        ),
+    );
+
+    entity
+}
+
+/// Friendly to the player, but not to orcs. Carries a potion they can be talked into giving away.
+fn villager(world: &mut World, config: &Config, x: i32, y: i32) -> anyhow::Result<Entity> {
+    let entity = world.spawn((
+        Monster,
+        Faction::Townsfolk,
+        Speaker {
+            dialogue: "villager".into(),
+        },
+        Name("Villager".into()),
+        Position { x, y },
+        config
+            .villager
+            .to_renderable_with_render_order(RENDER_ORDER_MONSTER),
+        Viewshed::with_range(8),
+        BlocksTile,
+        CombatStats {
+            max_hp: 8,
+            hp: 8,
+            defense: 0,
+            power: 2,
+        },
+    ));
+
+    let potion = health_potion(world, config, x, y);
+    add_to_inventory(world, potion, entity)?;
+
+    Ok(entity)
 }
 
 fn monster<S: Into<String>>(
@@ -74,9 +269,11 @@ fn monster<S: Into<String>>(
     y: i32,
     renderable: Renderable,
     name: S,
+    faction: Faction,
 ) -> Entity {
     world.spawn((
         Monster,
+        faction,
         Name(name.into()),
         Position { x, y },
         renderable,
@@ -115,9 +312,69 @@ fn get_random_points_in_rect(
     points
 }
 
+/// Spawn monsters and items in every room except the first, where the player starts, and maybe a
+/// vendor
+pub fn rng_level_entities(
+    world: &mut World,
+    config: &Config,
+    rooms: &[Rect],
+) -> anyhow::Result<()> {
+    for room in rooms.iter().skip(1) {
+        rng_room_entities(world, config, room)?;
+    }
+
+    let vendor_room = world.resource::<RandomNumberGenerator>()?.map(|mut rng| {
+        if rooms.len() > 1 && rng.roll_dice(1, 6) <= VENDOR_CHANCE {
+            Some(rng.range(1, rooms.len() as i32) as usize)
+        } else {
+            None
+        }
+    })?;
+
+    if let Some(room_index) = vendor_room {
+        let room = &rooms[room_index];
+
+        // Vendors keep to the corner opposite the brazier
+        let (x, y) = (room.x2, room.y1 + 1);
+
+        let is_occupied = world
+            .query::<&Position>()
+            .into_iter()
+            .any(|(_, pos)| pos.x == x && pos.y == y);
+
+        if !is_occupied {
+            vendor(world, config, x, y)?;
+        }
+    }
+
+    let villager_point = world.resource::<RandomNumberGenerator>()?.map(|mut rng| {
+        if rooms.len() > 1 && rng.roll_dice(1, 6) <= VILLAGER_CHANCE {
+            let room = &rooms[rng.range(1, rooms.len() as i32) as usize];
+            get_random_points_in_rect(room, &mut rng, 1).pop()
+        } else {
+            None
+        }
+    })?;
+
+    if let Some((x, y)) = villager_point {
+        let is_occupied = world
+            .query::<&Position>()
+            .into_iter()
+            .any(|(_, pos)| pos.x == x && pos.y == y);
+
+        if !is_occupied {
+            villager(world, config, x, y)?;
+        }
+    }
+
+    Ok(())
+}
+
 pub fn rng_room_entities(world: &mut World, config: &Config, room: &Rect) -> anyhow::Result<()> {
     let monster_spawn_points;
     let item_spawn_points;
+    let has_brazier;
+    let gold_pile;
 
     {
         let mut rng = world.query::<&mut RandomNumberGenerator>();
@@ -131,6 +388,23 @@ pub fn rng_room_entities(world: &mut World, config: &Config, room: &Rect) -> any
 
         monster_spawn_points = get_random_points_in_rect(&room, rng, num_monsters);
         item_spawn_points = get_random_points_in_rect(&room, rng, num_items);
+        has_brazier = rng.roll_dice(1, 6) <= BRAZIER_CHANCE;
+
+        gold_pile = if rng.roll_dice(1, 6) <= GOLD_CHANCE {
+            let amount = rng.roll_dice(3, 6);
+            get_random_points_in_rect(room, rng, 1)
+                .pop()
+                .map(|point| (point, amount))
+        } else {
+            None
+        };
+    }
+
+    // Braziers go in a corner, where they can't block the stairs in the middle of the room
+    let brazier_point = (room.x1 + 1, room.y1 + 1);
+
+    if has_brazier && !monster_spawn_points.contains(&brazier_point) {
+        brazier(world, config, brazier_point.0, brazier_point.1);
     }
 
     for (x, y) in monster_spawn_points {
@@ -138,12 +412,72 @@ pub fn rng_room_entities(world: &mut World, config: &Config, room: &Rect) -> any
     }
 
     for (x, y) in item_spawn_points {
-        health_potion(world, config, x, y);
+        rng_item(world, config, x, y)?;
+    }
+
+    if let Some(((x, y), amount)) = gold_pile {
+        gold(world, config, x, y, amount);
     }
 
     Ok(())
 }
 
+pub fn rng_item(world: &mut World, config: &Config, x: i32, y: i32) -> anyhow::Result<Entity> {
+    let dice_roll = world
+        .resource::<RandomNumberGenerator>()?
+        .map(|mut rng| rng.roll_dice(1, 12))?;
+
+    let entity = match dice_roll {
+        1 => telepathy_potion(world, config, x, y),
+        2 => magic_mapping_scroll(world, config, x, y),
+        3 => detection_amulet(world, config, x, y),
+        4 => identify_scroll(world, config, x, y),
+        5 => remove_curse_scroll(world, config, x, y),
+        6 => dagger(world, config, x, y)?,
+        7 => shield(world, config, x, y)?,
+        _ => health_potion(world, config, x, y),
+    };
+
+    Ok(entity)
+}
+
+/// Roll on a loot table, spawning the item dropped at `(x, y)` if there is one. Orcs carry weapons
+/// and armor, goblins hoard gold and trinkets.
+pub fn rng_loot(
+    world: &mut World,
+    config: &Config,
+    table: LootTable,
+    x: i32,
+    y: i32,
+) -> anyhow::Result<Option<Entity>> {
+    let (roll, gold_amount) = world
+        .resource::<RandomNumberGenerator>()?
+        .map(|mut rng| (rng.roll_dice(1, 6), rng.roll_dice(2, 6)))?;
+
+    let entity = match (table, roll) {
+        (LootTable::Orc, 1) => Some(dagger(world, config, x, y)?),
+        (LootTable::Orc, 2) => Some(shield(world, config, x, y)?),
+        (LootTable::Goblin, 1) => Some(rng_item(world, config, x, y)?),
+        (LootTable::Goblin, 2) | (LootTable::Goblin, 3) => {
+            Some(gold(world, config, x, y, gold_amount))
+        }
+        _ => None,
+    };
+
+    Ok(entity)
+}
+
+/// Spawn a new item of a kind which stacks, e.g. to split one off a stack
+pub fn stack_item(world: &mut World, config: &Config, kind: StackKind, x: i32, y: i32) -> Entity {
+    match kind {
+        StackKind::HealthPotion => health_potion(world, config, x, y),
+        StackKind::TelepathyPotion => telepathy_potion(world, config, x, y),
+        StackKind::MagicMappingScroll => magic_mapping_scroll(world, config, x, y),
+        StackKind::IdentifyScroll => identify_scroll(world, config, x, y),
+        StackKind::RemoveCurseScroll => remove_curse_scroll(world, config, x, y),
+    }
+}
+
 pub fn health_potion(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
     world.spawn((
         Position { x, y },
@@ -152,6 +486,267 @@ pub fn health_potion(world: &mut World, config: &Config, x: i32, y: i32) -> Enti
             .to_renderable_with_render_order(RENDER_ORDER_ITEM),
         Name("Health Potion".into()),
         Item,
+        Consumable,
         HealingItem { heal_amount: 8 },
+        Fragile,
+        Stack {
+            kind: StackKind::HealthPotion,
+            quantity: 1,
+        },
+        Weight { pounds: 1.0 },
+        Value { gold: 20 },
+    ))
+}
+
+pub fn telepathy_potion(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
+    world.spawn((
+        Position { x, y },
+        config
+            .telepathy_potion
+            .to_renderable_with_render_order(RENDER_ORDER_ITEM),
+        Name("Potion of Telepathy".into()),
+        Item,
+        Consumable,
+        TelepathyItem { duration: 40 },
+        Fragile,
+        Stack {
+            kind: StackKind::TelepathyPotion,
+            quantity: 1,
+        },
+        Weight { pounds: 1.0 },
+        Value { gold: 40 },
+    ))
+}
+
+pub fn magic_mapping_scroll(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
+    world.spawn((
+        Position { x, y },
+        config
+            .magic_mapping_scroll
+            .to_renderable_with_render_order(RENDER_ORDER_ITEM),
+        Name("Scroll of Magic Mapping".into()),
+        Item,
+        Consumable,
+        MagicMappingItem,
+        Stack {
+            kind: StackKind::MagicMappingScroll,
+            quantity: 1,
+        },
+        Weight { pounds: 0.5 },
+        Value { gold: 60 },
+    ))
+}
+
+pub fn identify_scroll(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
+    world.spawn((
+        Position { x, y },
+        config
+            .identify_scroll
+            .to_renderable_with_render_order(RENDER_ORDER_ITEM),
+        Name("Scroll of Identify".into()),
+        Item,
+        Consumable,
+        IdentifyItem,
+        Stack {
+            kind: StackKind::IdentifyScroll,
+            quantity: 1,
+        },
+        Weight { pounds: 0.5 },
+        Value { gold: 30 },
+    ))
+}
+
+pub fn remove_curse_scroll(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
+    world.spawn((
+        Position { x, y },
+        config
+            .remove_curse_scroll
+            .to_renderable_with_render_order(RENDER_ORDER_ITEM),
+        Name("Scroll of Remove Curse".into()),
+        Item,
+        Consumable,
+        RemoveCurseItem,
+        Stack {
+            kind: StackKind::RemoveCurseScroll,
+            quantity: 1,
+        },
+        Weight { pounds: 0.5 },
+        Value { gold: 50 },
     ))
 }
+
+/// Give new equipment a random enchantment, which is negative if it is cursed
+fn rng_enchant(world: &mut World, item: Entity) -> anyhow::Result<()> {
+    let (level, is_cursed) = world
+        .resource::<RandomNumberGenerator>()?
+        .map(|mut rng| match rng.roll_dice(1, 6) {
+            roll if roll <= CURSED_CHANCE => (-rng.roll_dice(1, 2), true),
+            roll if roll > 6 - BLESSED_CHANCE => (rng.roll_dice(1, 2), false),
+            _ => (0, false),
+        })?;
+
+    world.insert_one(
+        item,
+        Enchantment {
+            level,
+            is_known: false,
+        },
+    )?;
+
+    if is_cursed {
+        world.insert_one(item, Cursed)?;
+    }
+
+    Ok(())
+}
+
+pub fn dagger(world: &mut World, config: &Config, x: i32, y: i32) -> anyhow::Result<Entity> {
+    let entity = world.spawn((
+        Position { x, y },
+        config
+            .dagger
+            .to_renderable_with_render_order(RENDER_ORDER_ITEM),
+        Name("Dagger".into()),
+        Item,
+        Equippable {
+            slot: EquipmentSlot::Melee,
+        },
+        MeleePowerBonus { power: 2 },
+        Weight { pounds: 1.0 },
+        Value { gold: 30 },
+    ));
+
+    rng_enchant(world, entity)?;
+
+    Ok(entity)
+}
+
+pub fn shield(world: &mut World, config: &Config, x: i32, y: i32) -> anyhow::Result<Entity> {
+    let entity = world.spawn((
+        Position { x, y },
+        config
+            .shield
+            .to_renderable_with_render_order(RENDER_ORDER_ITEM),
+        Name("Shield".into()),
+        Item,
+        Equippable {
+            slot: EquipmentSlot::Shield,
+        },
+        DefenseBonus { defense: 1 },
+        Weight { pounds: 6.0 },
+        Value { gold: 40 },
+    ));
+
+    rng_enchant(world, entity)?;
+
+    Ok(entity)
+}
+
+pub fn detection_amulet(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
+    world.spawn((
+        Position { x, y },
+        config
+            .detection_amulet
+            .to_renderable_with_render_order(RENDER_ORDER_ITEM),
+        Name("Amulet of Monster Detection".into()),
+        Item,
+        DetectsMonsters { range: 12 },
+        Weight { pounds: 0.5 },
+        Value { gold: 100 },
+    ))
+}
+
+pub fn brazier(world: &mut World, config: &Config, x: i32, y: i32) -> Entity {
+    world.spawn((
+        Position { x, y },
+        config
+            .brazier
+            .to_renderable_with_render_order(RENDER_ORDER_FEATURE),
+        Name("Brazier".into()),
+        BlocksTile,
+        LightSource {
+            radius: 6,
+            color: RGB::from_f32(1.0, 0.6, 0.3),
+        },
+    ))
+}
+
+pub fn gold(world: &mut World, config: &Config, x: i32, y: i32, amount: i32) -> Entity {
+    world.spawn((
+        Position { x, y },
+        config
+            .gold
+            .to_renderable_with_render_order(RENDER_ORDER_ITEM),
+        Name(format!("{} Gold", amount)),
+        Item,
+        Gold { amount },
+    ))
+}
+
+/// The corpse of the creature called `name`
+pub fn corpse(world: &mut World, config: &Config, name: &str, x: i32, y: i32) -> Entity {
+    world.spawn((
+        Position { x, y },
+        config
+            .corpse
+            .to_renderable_with_render_order(RENDER_ORDER_CORPSE),
+        Name(format!("{} corpse", name)),
+        Item,
+        Corpse,
+        Decays {
+            turns_left: CORPSE_DECAY_TURNS,
+        },
+        Weight { pounds: 20.0 },
+    ))
+}
+
+/// A splatter of blood on the floor, unless there already is one
+pub fn blood(world: &mut World, config: &Config, x: i32, y: i32) -> Option<Entity> {
+    let is_bloody = world
+        .query::<(&Decal, &Position)>()
+        .into_iter()
+        .any(|(_, (_, pos))| pos.x == x && pos.y == y);
+
+    if is_bloody {
+        return None;
+    }
+
+    Some(
+        world.spawn((
+            Position { x, y },
+            config
+                .blood
+                .to_renderable_with_render_order(RENDER_ORDER_DECAL),
+            Name("Blood".into()),
+            Decal,
+            Decays {
+                turns_left: BLOOD_DECAY_TURNS,
+            },
+        )),
+    )
+}
+
+/// A vendor with a few random items for sale
+pub fn vendor(world: &mut World, config: &Config, x: i32, y: i32) -> anyhow::Result<Entity> {
+    let entity = world.spawn((
+        Vendor,
+        Faction::Townsfolk,
+        Name("Shopkeeper".into()),
+        Position { x, y },
+        config
+            .vendor
+            .to_renderable_with_render_order(RENDER_ORDER_MONSTER),
+        BlocksTile,
+    ));
+
+    let num_stock = world
+        .resource::<RandomNumberGenerator>()?
+        .map(|mut rng| rng.roll_dice(1, MAX_VENDOR_STOCK - 1) + 1)?;
+
+    for _ in 0..num_stock {
+        let item = rng_item(world, config, x, y)?;
+        add_to_inventory(world, item, entity)?;
+    }
+
+    Ok(entity)
+}
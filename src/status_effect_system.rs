@@ -0,0 +1,98 @@
+use crate::prelude::*;
+
+/// Tick down status effects each turn, issuing damage for those which hurt, e.g. poison
+pub fn status_effect_system(world: &mut World) -> anyhow::Result<()> {
+    if world.resource_clone::<RunState>()? != RunState::PlayerTurn {
+        return Ok(());
+    }
+
+    let player = world.resource_entity::<Player>().ok();
+
+    let mut damage_commands_batch = Vec::new();
+    let mut expired = Vec::new();
+
+    for (entity, (poisoned, stats)) in world.query::<(&mut Poisoned, &CombatStats)>().into_iter() {
+        if stats.hp <= 0 {
+            continue;
+        }
+
+        damage_commands_batch.push(DamageCommand {
+            entity,
+            amount: poisoned.damage,
+            damage_type: DamageType::Poison,
+            source: poisoned.source,
+        });
+
+        poisoned.turns_left -= 1;
+
+        if poisoned.turns_left <= 0 {
+            expired.push(entity);
+        }
+    }
+
+    for entity in expired {
+        world.remove_one::<Poisoned>(entity)?;
+
+        if Some(entity) == player {
+            GameLog::resource_push(world, "The poison wears off.")?;
+        }
+    }
+
+    world.spawn_batch_commands(damage_commands_batch);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::config_from_str, damage_system::damage_system};
+
+    #[test]
+    fn test_poison() {
+        let config = config_from_str("").unwrap();
+
+        let mut world = World::new();
+        world.spawn_resource(GameLog::new(), ()).unwrap();
+        world.spawn_resource(RunState::PlayerTurn, ()).unwrap();
+
+        let stats = CombatStats {
+            max_hp: 10,
+            hp: 10,
+            defense: 0,
+            power: 1,
+        };
+        let goblin = world.spawn((
+            Name("Goblin".into()),
+            stats,
+            Poisoned {
+                turns_left: 2,
+                damage: 2,
+                source: None,
+            },
+        ));
+        let orc = world.spawn((
+            Name("Orc".into()),
+            stats,
+            Poisoned {
+                turns_left: 2,
+                damage: 2,
+                source: None,
+            },
+            Resistant(vec![DamageType::Poison]),
+        ));
+
+        let hp = |world: &World, entity| world.get::<CombatStats>(entity).unwrap().hp;
+
+        for _ in 0..3 {
+            status_effect_system(&mut world).unwrap();
+            damage_system(&mut world, &config).unwrap();
+            world.clear_commands();
+        }
+
+        assert_eq!(hp(&world, goblin), 6);
+        assert_eq!(hp(&world, orc), 8);
+        assert!(world.get::<Poisoned>(goblin).is_err());
+        assert!(world.get::<Poisoned>(orc).is_err());
+    }
+}
@@ -0,0 +1,381 @@
+use bracket_lib::random::RandomNumberGenerator;
+
+use crate::{
+    combat_rules::{armor_class, roll_attack, AttackResult, DamageDice},
+    despawn_entities_system::queue_despawn_batch,
+    equipment::{equipment_bonus, try_unequip},
+    faction::{entity_reaction, Reaction},
+    identification::{display_name, identify_item},
+    inventory::{get_inventory_list, is_visible_to_player, take_one_from_inventory},
+    particle::{spawn_burst, spawn_number},
+    player::{key_to_direction, PlayerAction, PlayerInput},
+    prelude::*,
+    projectile::{flight_path, line_of_fire, spawn_projectile_animation},
+};
+
+/// Farthest anything can be thrown, in tiles
+pub const THROW_RANGE: f32 = 6.0;
+
+/// Thrown items deal 1 damage on average, plus this much for each pound they weigh up to
+/// `MAX_WEIGHT_DAMAGE`
+const DAMAGE_PER_POUND: f32 = 0.5;
+const MAX_WEIGHT_DAMAGE: i32 = 5;
+
+/// How far the effect of a shattered item reaches from where it lands
+const SHATTER_RADIUS: f32 = 1.5;
+
+pub fn throw_item_system(world: &mut World, config: &Config) -> anyhow::Result<()> {
+    let player = world.resource_entity::<Player>().ok();
+
+    let throw_commands = world
+        .query::<&ThrowItemCommand>()
+        .into_iter()
+        .map(|(_, cmd)| *cmd)
+        .collect::<Vec<_>>();
+
+    let mut items_to_despawn = Vec::new();
+    let mut damage_commands_batch = Vec::new();
+
+    for cmd in throw_commands {
+        let from = match world.get::<Position>(cmd.thrower) {
+            Ok(pos) => *pos,
+            Err(_) => continue,
+        };
+
+        if !try_unequip(world, cmd.item)? {
+            continue;
+        }
+
+        let item = take_one_from_inventory(world, config, cmd.item)?;
+        let item_name = display_name(world, item).unwrap_or_default();
+        let is_thrower_player = Some(cmd.thrower) == player;
+
+        if is_thrower_player {
+            GameLog::resource_push(world, format!("You throw the {}.", item_name))?;
+        } else if is_visible_to_player(world, cmd.thrower) {
+            let thrower_name = display_name(world, cmd.thrower).unwrap_or_default();
+            GameLog::resource_push(
+                world,
+                format!("The {} throws a {}.", thrower_name, item_name),
+            )?;
+        }
+
+        let (path, hit) = world
+            .resource::<TileMap>()?
+            .map(|map| line_of_fire(world, &map, from.into(), cmd.target.into()))?;
+        let landing = path.last().map_or(from, |point| Position {
+            x: point.x,
+            y: point.y,
+        });
+
+        let glyph = world.get::<Renderable>(item).map(|r| (r.glyph, r.fg));
+        if let Ok((glyph, fg)) = glyph {
+            spawn_projectile_animation(world, path, glyph, fg);
+        }
+
+        if world.get::<Fragile>(item).is_ok() {
+            shatter(world, config, item, landing, is_thrower_player)?;
+            items_to_despawn.push(item);
+            continue;
+        }
+
+        if let Some(defender) = hit {
+            let defense = world.get::<CombatStats>(defender)?.defense
+                + equipment_bonus(world, defender).defense;
+            let to_hit = world
+                .get::<CombatStats>(cmd.thrower)
+                .map(|stats| stats.power)
+                .unwrap_or(0);
+            let damage_dice = DamageDice::with_average(throw_damage(world, item));
+
+            let result = world
+                .resource::<RandomNumberGenerator>()?
+                .map(|mut rng| roll_attack(&mut rng, to_hit, armor_class(defense), damage_dice))?;
+
+            let defender_name = if Some(defender) == player {
+                "you".to_string()
+            } else {
+                format!("the {}", display_name(world, defender).unwrap_or_default())
+            };
+
+            let (message, damage) = match result {
+                AttackResult::Fumble | AttackResult::Miss => {
+                    (format!("The {} misses {}.", item_name, defender_name), 0)
+                }
+                AttackResult::Hit { damage } => (
+                    format!(
+                        "The {} hits {} for {} hp.",
+                        item_name, defender_name, damage
+                    ),
+                    damage,
+                ),
+                AttackResult::Critical { damage } => (
+                    format!(
+                        "The {} critically hits {} for {} hp!",
+                        item_name, defender_name, damage
+                    ),
+                    damage,
+                ),
+            };
+
+            GameLog::resource_push_combat(world, message)?;
+
+            if damage > 0 {
+                damage_commands_batch.push(DamageCommand {
+                    entity: defender,
+                    amount: damage,
+                    damage_type: DamageType::Physical,
+                    source: Some(cmd.thrower),
+                });
+            }
+        }
+
+        world.insert_one(item, landing)?;
+    }
+
+    world.spawn_batch_commands(damage_commands_batch);
+    queue_despawn_batch(world, items_to_despawn);
+
+    Ok(())
+}
+
+/// Why the player can't throw at `target`, if they can't
+pub fn throw_target_error(world: &World, target: Position) -> Option<&'static str> {
+    let player = world.resource_entity::<Player>().ok()?;
+    let from = *world.get::<Position>(player).ok()?;
+
+    let mut map_query = world.query::<&TileMap>();
+    let map = map_query.into_iter().next()?.1;
+
+    if target == from {
+        Some("Pick a target to throw at.")
+    } else if !map.is_tile_visible(target.x, target.y) {
+        Some("You can't see there.")
+    } else if DistanceAlg::Pythagoras.distance2d(from.into(), target.into()) > THROW_RANGE {
+        Some("That is out of range.")
+    } else if flight_path(from.into(), target.into(), |p| {
+        !map.is_tile_walkable(p.x, p.y)
+    })
+    .last()
+        != Some(&target.into())
+    {
+        Some("There is a wall in the way.")
+    } else {
+        None
+    }
+}
+
+/// Start picking a target for throwing the item at `index` in the player's inventory list, with the
+/// cursor on the nearest hostile creature in range, or on the player if there is none
+pub fn start_throw_targeting(world: &World, index: usize) -> anyhow::Result<RunState> {
+    let player = world.resource_entity::<Player>()?;
+    let pos = *world.get::<Position>(player)?;
+
+    if get_inventory_list(world, player).get(index).is_none() {
+        return Ok(RunState::AwaitingInput);
+    }
+
+    let target = world
+        .query::<(&Position, &CombatStats)>()
+        .into_iter()
+        .filter(|(entity, (target, _))| {
+            entity_reaction(world, player, *entity) == Reaction::Hostile
+                && throw_target_error(world, **target).is_none()
+        })
+        .map(|(_, (target, _))| *target)
+        .min_by_key(|target| {
+            DistanceAlg::PythagorasSquared.distance2d(pos.into(), (*target).into()) as i32
+        })
+        .unwrap_or(pos);
+
+    Ok(RunState::ThrowTarget {
+        index,
+        x: target.x,
+        y: target.y,
+    })
+}
+
+/// Move the throw target cursor, throw at the target, or cancel
+pub fn ui_throw_target_input(
+    context: &mut BTerm,
+    world: &World,
+    index: usize,
+    x: i32,
+    y: i32,
+) -> PlayerInput {
+    let throw_at = |x, y| {
+        if throw_target_error(world, Position { x, y }).is_none() {
+            PlayerInput::Action(PlayerAction::Throw { index, x, y })
+        } else {
+            PlayerInput::RunState(RunState::ThrowTarget { index, x, y })
+        }
+    };
+
+    if context.left_click {
+        let (x, y) = context.mouse_pos();
+        return throw_at(x, y);
+    }
+
+    let key = match context.key {
+        Some(key) => key,
+        None => return PlayerInput::None,
+    };
+
+    match key {
+        VirtualKeyCode::Escape => PlayerInput::RunState(RunState::AwaitingInput),
+        VirtualKeyCode::Return | VirtualKeyCode::T => throw_at(x, y),
+        _ => match key_to_direction(key) {
+            Some((dx, dy)) => {
+                let (width, height) = match world.query::<&TileMap>().into_iter().next() {
+                    Some((_, map)) => (map.get_width(), map.get_height()),
+                    None => return PlayerInput::RunState(RunState::AwaitingInput),
+                };
+
+                PlayerInput::RunState(RunState::ThrowTarget {
+                    index,
+                    x: (x + dx).max(0).min(width - 1),
+                    y: (y + dy).max(0).min(height - 1),
+                })
+            }
+            None => PlayerInput::None,
+        },
+    }
+}
+
+/// Average damage a thrown item deals. Weapons hit as hard as they would in melee.
+fn throw_damage(world: &World, item: Entity) -> i32 {
+    let weight = world
+        .get::<Weight>(item)
+        .map(|weight| weight.pounds)
+        .unwrap_or(0.0);
+    let weight_damage = ((weight * DAMAGE_PER_POUND) as i32).min(MAX_WEIGHT_DAMAGE);
+
+    let weapon_damage = match world.get::<MeleePowerBonus>(item) {
+        Ok(bonus) => {
+            bonus.power
+                + world
+                    .get::<Enchantment>(item)
+                    .map(|enchantment| enchantment.level)
+                    .unwrap_or(0)
+        }
+        Err(_) => 0,
+    };
+
+    i32::max(0, 1 + weight_damage + weapon_damage)
+}
+
+/// Break a fragile item where it lands, applying its effect to every creature around it
+fn shatter(
+    world: &mut World,
+    config: &Config,
+    item: Entity,
+    landing: Position,
+    is_thrower_player: bool,
+) -> anyhow::Result<()> {
+    let player = world.resource_entity::<Player>().ok();
+    let item_name = display_name(world, item).unwrap_or_default();
+
+    let is_landing_visible = world
+        .resource::<TileMap>()?
+        .map(|map| map.is_tile_visible(landing.x, landing.y))?;
+
+    if is_landing_visible {
+        GameLog::resource_push(world, format!("The {} shatters!", item_name))?;
+    }
+
+    if let Ok(fg) = world.get::<Renderable>(item).map(|render| render.fg) {
+        spawn_burst(world, landing.x, landing.y, SHATTER_RADIUS as i32, fg);
+    }
+
+    let heal_amount = world
+        .get::<HealingItem>(item)
+        .map(|healing| healing.heal_amount)
+        .ok();
+    let telepathy_duration = world
+        .get::<TelepathyItem>(item)
+        .map(|telepathy| telepathy.duration)
+        .ok();
+
+    let splashed = world
+        .query::<(&Position, &CombatStats)>()
+        .into_iter()
+        .filter(|(_, (pos, stats))| {
+            stats.hp > 0
+                && DistanceAlg::Pythagoras.distance2d(landing.into(), (**pos).into())
+                    <= SHATTER_RADIUS
+        })
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    for entity in splashed.iter().copied() {
+        let is_player = Some(entity) == player;
+        let name = display_name(world, entity).unwrap_or_default();
+
+        if let Some(heal_amount) = heal_amount {
+            {
+                let mut stats = world.get_mut::<CombatStats>(entity)?;
+                stats.hp = i32::min(stats.max_hp, stats.hp + heal_amount);
+            }
+            spawn_number(
+                world,
+                entity,
+                &format!("+{}", heal_amount),
+                config.particle_heal.fg,
+            );
+
+            if is_player {
+                GameLog::resource_push(world, format!("It heals you for {} hp.", heal_amount))?;
+            } else if is_visible_to_player(world, entity) {
+                GameLog::resource_push(world, format!("The {} looks healthier.", name))?;
+            }
+        }
+
+        if let Some(duration) = telepathy_duration {
+            world.insert_one(
+                entity,
+                Telepathic {
+                    turns_left: duration,
+                },
+            )?;
+
+            if is_player {
+                GameLog::resource_push(world, "You sense the minds around you.")?;
+            }
+        }
+    }
+
+    // Seeing what it does reveals what it was
+    if is_thrower_player && !splashed.is_empty() && identify_item(world, item) {
+        let name = world.get::<Name>(item)?;
+        GameLog::resource_push(world, format!("It was a {}.", name.as_str()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throw_damage() {
+        let mut world = World::new();
+
+        let potion = world.spawn((Weight { pounds: 1.0 },));
+        let shield = world.spawn((Weight { pounds: 6.0 },));
+        let corpse = world.spawn((Weight { pounds: 20.0 },));
+        let dagger = world.spawn((
+            Weight { pounds: 1.0 },
+            MeleePowerBonus { power: 2 },
+            Enchantment {
+                level: 1,
+                is_known: false,
+            },
+        ));
+
+        assert_eq!(throw_damage(&world, potion), 1);
+        assert_eq!(throw_damage(&world, shield), 4);
+        assert_eq!(throw_damage(&world, corpse), 1 + MAX_WEIGHT_DAMAGE);
+        assert_eq!(throw_damage(&world, dagger), 4);
+    }
+}
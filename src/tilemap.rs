@@ -5,14 +5,26 @@ use std::{
     vec,
 };
 
-use crate::prelude::*;
+use crate::{
+    particle::{is_particle_shown, Particles},
+    prelude::*,
+    projectile::{animation_tile, ProjectileAnimations},
+};
 
 const EMPTY_ENTITY_ARRAY: &'static [Entity] = &[];
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+/// Tiles with less light than this (in their brightest color channel) can't be seen without
+/// darkvision
+const MIN_VISIBLE_LIGHT: f32 = 0.15;
+
+/// Visible tiles are never tinted darker than this, so dimly lit tiles stay readable
+const MIN_LIGHT_TINT: f32 = 0.35;
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum TileType {
     Wall,
     Floor,
+    DownStairs,
 }
 
 impl TileType {
@@ -22,6 +34,8 @@ impl TileType {
             (TileType::Wall, false) => config.wall.fog_fg,
             (TileType::Floor, true) => config.floor.fg,
             (TileType::Floor, false) => config.floor.fog_fg,
+            (TileType::DownStairs, true) => config.down_stairs.fg,
+            (TileType::DownStairs, false) => config.down_stairs.fog_fg,
         }
     }
 
@@ -31,6 +45,8 @@ impl TileType {
             (TileType::Wall, false) => config.wall.fog_bg,
             (TileType::Floor, true) => config.floor.bg,
             (TileType::Floor, false) => config.floor.fog_bg,
+            (TileType::DownStairs, true) => config.down_stairs.bg,
+            (TileType::DownStairs, false) => config.down_stairs.fog_bg,
         }
     }
 
@@ -38,30 +54,71 @@ impl TileType {
         match self {
             TileType::Wall => config.wall.glyph,
             TileType::Floor => config.floor.glyph,
+            TileType::DownStairs => config.down_stairs.glyph,
         }
     }
 
     fn is_opaque(&self) -> bool {
         match self {
             TileType::Wall => true,
-            TileType::Floor => false,
+            TileType::Floor | TileType::DownStairs => false,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TileType::Wall => "Wall",
+            TileType::Floor => "Floor",
+            TileType::DownStairs => "Down stairs",
+        }
+    }
+
+    /// Plain-text character, e.g. for the morgue file
+    pub fn ascii(&self) -> char {
+        match self {
+            TileType::Wall => '#',
+            TileType::Floor => '.',
+            TileType::DownStairs => '>',
+        }
+    }
+
+    fn is_walkable(&self) -> bool {
+        match self {
+            TileType::Wall => false,
+            TileType::Floor | TileType::DownStairs => true,
         }
     }
 }
 
+/// What the player last saw of an entity, kept after the tile goes out of sight
+#[derive(Debug, Clone)]
+pub struct RememberedEntity {
+    pub description: String,
+    /// Drawn in fog colors while the tile is out of sight. `None` for entities which move around.
+    pub renderable: Option<Renderable>,
+}
+
 pub struct TileMap {
     tiles: Vec<TileType>,
     rooms: Vec<Rect>,
     width: i32,
     height: i32,
+    depth: i32,
     revealed_tiles: Vec<bool>,
     visible_tiles: Vec<bool>,
-    blocked_tiles: Vec<bool>,
+    /// Number of `BlocksTile` entities indexed on each tile
+    blocker_counts: Vec<u32>,
     tile_content: Vec<Vec<Entity>>,
+    remembered_content: Vec<Vec<RememberedEntity>>,
+    /// Content each visible tile was last remembered from, or `None` if it was out of view
+    remembered_from: Vec<Option<Vec<Entity>>>,
+    /// Tiles in lit rooms, which are bright even without a light source
+    ambient_lit_tiles: Vec<bool>,
+    light: Vec<RGB>,
 }
 
 impl TileMap {
-    fn blank(width: i32, height: i32, tile_type: TileType) -> Self {
+    fn blank(width: i32, height: i32, depth: i32, tile_type: TileType) -> Self {
         assert!(width > 0);
         assert!(height > 0);
 
@@ -72,23 +129,50 @@ impl TileMap {
             rooms: Vec::new(),
             width,
             height,
+            depth,
             revealed_tiles: vec![false; num_tiles],
             visible_tiles: vec![false; num_tiles],
-            blocked_tiles: vec![false; num_tiles],
+            blocker_counts: vec![0; num_tiles],
             tile_content: vec![Vec::new(); num_tiles],
+            remembered_content: vec![Vec::new(); num_tiles],
+            remembered_from: vec![None; num_tiles],
+            ambient_lit_tiles: vec![false; num_tiles],
+            light: vec![RGB::from_f32(0.0, 0.0, 0.0); num_tiles],
+        }
+    }
+
+    /// Build a map from rows of tiles drawn as their `TileType::ascii` characters
+    #[cfg(test)]
+    pub fn from_ascii(rows: &[&str]) -> Self {
+        let width = rows.first().map_or(0, |row| row.chars().count()) as i32;
+        let mut map = TileMap::blank(width, rows.len() as i32, 1, TileType::Wall);
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let tile_type = [TileType::Wall, TileType::Floor, TileType::DownStairs]
+                    .iter()
+                    .copied()
+                    .find(|tile_type| tile_type.ascii() == c)
+                    .unwrap_or(TileType::Wall);
+                map.apply_tile(x as i32, y as i32, tile_type);
+            }
         }
+
+        map
     }
 
-    pub fn rooms_and_cooridors(width: i32, height: i32) -> Self {
-        let mut map = Self::blank(width, height, TileType::Wall);
+    pub fn rooms_and_cooridors(
+        width: i32,
+        height: i32,
+        depth: i32,
+        rng: &mut RandomNumberGenerator,
+    ) -> Self {
+        let mut map = Self::blank(width, height, depth, TileType::Wall);
 
         const MAX_ROOMS: i32 = 30;
         const MIN_SIZE: i32 = 6;
         const MAX_SIZE: i32 = 10;
 
-        // TODO: Use resource
-        let mut rng = RandomNumberGenerator::new();
-
         for _ in 0..MAX_ROOMS {
             let w = rng.range(MIN_SIZE, MAX_SIZE);
             let h = rng.range(MIN_SIZE, MAX_SIZE);
@@ -101,6 +185,11 @@ impl TileMap {
             if !intersects_existing_room {
                 map.apply_rect(&new_room, TileType::Floor);
 
+                // Deeper levels have fewer lit rooms
+                if rng.range(0, depth + 2) < 2 {
+                    map.set_room_ambient_lit(&new_room);
+                }
+
                 if let Some(prev_room) = map.rooms.last() {
                     let (new_x, new_y) = new_room.center().to_tuple();
                     let (prev_x, prev_y) = prev_room.center().to_tuple();
@@ -118,6 +207,11 @@ impl TileMap {
             }
         }
 
+        if let Some(last_room) = map.rooms.last() {
+            let (stairs_x, stairs_y) = last_room.center().to_tuple();
+            map.apply_tile(stairs_x, stairs_y, TileType::DownStairs);
+        }
+
         map
     }
 
@@ -129,6 +223,10 @@ impl TileMap {
         self.height
     }
 
+    pub fn get_depth(&self) -> i32 {
+        self.depth
+    }
+
     pub fn get_rooms(&self) -> &[Rect] {
         &self.rooms
     }
@@ -162,32 +260,115 @@ impl TileMap {
         }
     }
 
-    pub fn set_tile_blocked(&mut self, x: i32, y: i32, is_blocked: bool) {
+    pub fn get_entities_on_tile(&self, x: i32, y: i32) -> &[Entity] {
         if let Some(index) = self.get_index(x, y) {
-            self.blocked_tiles[index] = is_blocked;
+            &self.tile_content[index]
+        } else {
+            EMPTY_ENTITY_ARRAY
         }
     }
 
-    pub fn reset_blocked_tiles(&mut self) {
-        for (i, is_blocked) in self.blocked_tiles.iter_mut().enumerate() {
-            *is_blocked = match self.tiles[i] {
-                TileType::Wall => true,
-                TileType::Floor => false,
+    /// Add an entity to the tile content index, counting it as a blocker if `blocks_tile`
+    pub fn index_entity(&mut self, x: i32, y: i32, entity: Entity, blocks_tile: bool) {
+        if let Some(index) = self.get_index(x, y) {
+            self.tile_content[index].push(entity);
+
+            if blocks_tile {
+                self.blocker_counts[index] += 1;
             }
         }
     }
 
-    pub fn get_entities_on_tile(&self, x: i32, y: i32) -> &[Entity] {
+    /// Remove an entity added with `index_entity`, with the same `blocks_tile`
+    pub fn unindex_entity(&mut self, x: i32, y: i32, entity: Entity, blocks_tile: bool) {
         if let Some(index) = self.get_index(x, y) {
-            &self.tile_content[index]
-        } else {
-            EMPTY_ENTITY_ARRAY
+            self.tile_content[index].retain(|e| *e != entity);
+
+            if blocks_tile {
+                self.blocker_counts[index] = self.blocker_counts[index].saturating_sub(1);
+            }
+        }
+    }
+
+    /// Move an indexed entity, e.g. as soon as a monster steps so others can't step onto the same
+    /// tile in the same turn
+    pub fn move_indexed_entity(
+        &mut self,
+        entity: Entity,
+        from: IndexedPosition,
+        to: IndexedPosition,
+    ) {
+        self.unindex_entity(from.position.x, from.position.y, entity, from.blocks_tile);
+        self.index_entity(to.position.x, to.position.y, entity, to.blocks_tile);
+    }
+
+    /// Replace the remembered content of every visible tile with what is on it now.
+    /// Tiles are only re-remembered when they come into view or their content changes.
+    /// `remember` returns `None` for entities which should not be remembered.
+    pub fn remember_visible_content<F>(&mut self, mut remember: F)
+    where
+        F: FnMut(Entity) -> Option<RememberedEntity>,
+    {
+        for (i, content) in self.tile_content.iter().enumerate() {
+            if !self.visible_tiles[i] {
+                self.remembered_from[i] = None;
+                continue;
+            }
+
+            if self.remembered_from[i].as_ref() == Some(content) {
+                continue;
+            }
+
+            self.remembered_content[i] = content
+                .iter()
+                .filter_map(|entity| remember(*entity))
+                .collect();
+            self.remembered_from[i] = Some(content.clone());
         }
     }
 
-    pub fn add_entity_to_tile_content(&mut self, x: i32, y: i32, entity: Entity) {
+    /// Set every tile's light back to its ambient level, before adding light sources
+    pub fn reset_light(&mut self) {
+        for (light, is_lit) in self.light.iter_mut().zip(self.ambient_lit_tiles.iter()) {
+            *light = if *is_lit {
+                RGB::from_f32(1.0, 1.0, 1.0)
+            } else {
+                RGB::from_f32(0.0, 0.0, 0.0)
+            };
+        }
+    }
+
+    pub fn add_light(&mut self, x: i32, y: i32, color: RGB) {
         if let Some(index) = self.get_index(x, y) {
-            self.tile_content[index].push(entity);
+            let light = self.light[index] + color;
+            self.light[index] = RGB::from_f32(light.r.min(1.0), light.g.min(1.0), light.b.min(1.0));
+        }
+    }
+
+    pub fn get_light_levels(&self) -> &[RGB] {
+        &self.light
+    }
+
+    pub fn is_tile_lit(&self, x: i32, y: i32) -> bool {
+        if let Some(index) = self.get_index(x, y) {
+            let light = self.light[index];
+            light.r.max(light.g).max(light.b) >= MIN_VISIBLE_LIGHT
+        } else {
+            false
+        }
+    }
+
+    /// Reveal every tile, e.g. from magic mapping
+    pub fn reveal_all_tiles(&mut self) {
+        for revealed in self.revealed_tiles.iter_mut() {
+            *revealed = true;
+        }
+    }
+
+    pub fn get_remembered_content(&self, x: i32, y: i32) -> &[RememberedEntity] {
+        match self.get_index(x, y) {
+            Some(index) => &self.remembered_content[index],
+            None => &[],
         }
     }
 
@@ -195,15 +376,53 @@ impl TileMap {
         for content in self.tile_content.iter_mut() {
             content.clear();
         }
+
+        for count in self.blocker_counts.iter_mut() {
+            *count = 0;
+        }
     }
 
-    // pub fn is_tile_revealed(&self, x: i32, y: i32) -> bool {
-    //     if let Some(index) = self.get_index(x, y) {
-    //         self.revealed_tiles[index]
-    //     } else {
-    //         false
-    //     }
-    // }
+    pub fn is_tile_revealed(&self, x: i32, y: i32) -> bool {
+        if let Some(index) = self.get_index(x, y) {
+            self.revealed_tiles[index]
+        } else {
+            false
+        }
+    }
+
+    /// Indices of revealed, walkable tiles which are next to at least one unrevealed tile
+    pub fn get_unexplored_frontier(&self) -> Vec<usize> {
+        (0..self.tiles.len())
+            .filter(|i| self.revealed_tiles[*i] && self.tiles[*i].is_walkable())
+            .filter(|i| {
+                let (x, y) = self.get_coords(*i);
+
+                (-1..=1).cartesian_product(-1..=1).any(|(dx, dy)| {
+                    matches!(
+                        self.get_index(x + dx, y + dy),
+                        Some(neighbor) if !self.revealed_tiles[neighbor]
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Coordinates of every revealed tile of the given type
+    pub fn find_revealed_tiles(&self, tile_type: TileType) -> Vec<(i32, i32)> {
+        (0..self.tiles.len())
+            .filter(|i| self.revealed_tiles[*i] && self.tiles[*i] == tile_type)
+            .map(|i| self.get_coords(i))
+            .collect()
+    }
+
+    /// Number of walkable tiles surrounding `(x, y)`
+    pub fn count_walkable_neighbors(&self, x: i32, y: i32) -> usize {
+        (-1..=1)
+            .cartesian_product(-1..=1)
+            .filter(|(dx, dy)| *dx != 0 || *dy != 0)
+            .filter(|(dx, dy)| matches!(self.get_tile(x + dx, y + dy), Some(tile) if tile.is_walkable()))
+            .count()
+    }
 
     pub fn is_tile_visible(&self, x: i32, y: i32) -> bool {
         if let Some(index) = self.get_index(x, y) {
@@ -213,14 +432,23 @@ impl TileMap {
         }
     }
 
+    pub fn is_tile_down_stairs(&self, x: i32, y: i32) -> bool {
+        self.get_tile(x, y) == Some(&TileType::DownStairs)
+    }
+
     pub fn is_tile_blocked(&self, x: i32, y: i32) -> bool {
         if let Some(index) = self.get_index(x, y) {
-            self.blocked_tiles[index]
+            !self.tiles[index].is_walkable() || self.blocker_counts[index] > 0
         } else {
             true
         }
     }
 
+    /// Whether the tile itself can be walked on or flown through, regardless of what is on it
+    pub fn is_tile_walkable(&self, x: i32, y: i32) -> bool {
+        self.get_tile(x, y).map_or(false, |tile| tile.is_walkable())
+    }
+
     pub fn get_index(&self, x: i32, y: i32) -> Option<usize> {
         if x < 0 || x >= self.width || y < 0 || y >= self.height {
             return None;
@@ -255,6 +483,17 @@ impl TileMap {
         }
     }
 
+    /// Light the room's floor and the walls around it
+    fn set_room_ambient_lit(&mut self, rect: &Rect) {
+        for y in rect.y1..=(rect.y2 + 1) {
+            for x in rect.x1..=(rect.x2 + 1) {
+                if let Some(index) = self.get_index(x, y) {
+                    self.ambient_lit_tiles[index] = true;
+                }
+            }
+        }
+    }
+
     fn apply_horizontal_line(&mut self, x1: i32, x2: i32, y: i32, tile_type: TileType) {
         for x in min(x1, x2)..=max(x1, x2) {
             self.apply_tile(x, y, tile_type);
@@ -319,19 +558,101 @@ impl Algorithm2D for TileMap {
     }
 }
 
+/// Pathing view of a `TileMap` which only walks over tiles the player has revealed
+pub struct RevealedTileMap<'a>(pub &'a TileMap);
+
+impl<'a> BaseMap for RevealedTileMap<'a> {
+    fn is_opaque(&self, index: usize) -> bool {
+        self.0.is_opaque(index)
+    }
+
+    fn get_pathing_distance(&self, index1: usize, index2: usize) -> f32 {
+        self.0.get_pathing_distance(index1, index2)
+    }
+
+    fn get_available_exits(&self, index: usize) -> SmallVec<[(usize, f32); 10]> {
+        self.0
+            .get_available_exits(index)
+            .into_iter()
+            .filter(|(i, _cost)| self.0.revealed_tiles[*i])
+            .collect()
+    }
+}
+
+impl<'a> Algorithm2D for RevealedTileMap<'a> {
+    fn dimensions(&self) -> Point {
+        self.0.dimensions()
+    }
+}
+
+/// Pathing view of a `TileMap` where the `goal` tile can be entered even if something blocks it, for
+/// pathing to a creature standing there
+pub struct GoalTileMap<'a> {
+    pub map: &'a TileMap,
+    pub goal: usize,
+}
+
+impl<'a> BaseMap for GoalTileMap<'a> {
+    fn is_opaque(&self, index: usize) -> bool {
+        self.map.is_opaque(index)
+    }
+
+    fn get_pathing_distance(&self, index1: usize, index2: usize) -> f32 {
+        self.map.get_pathing_distance(index1, index2)
+    }
+
+    fn get_available_exits(&self, index: usize) -> SmallVec<[(usize, f32); 10]> {
+        let mut exits = self.map.get_available_exits(index);
+
+        let (x, y) = self.map.get_coords(index);
+        let (goal_x, goal_y) = self.map.get_coords(self.goal);
+        let (dx, dy) = (goal_x - x, goal_y - y);
+
+        let is_goal_adjacent = (dx != 0 || dy != 0) && dx.abs() <= 1 && dy.abs() <= 1;
+        let is_goal_walkable =
+            matches!(self.map.get_tile(goal_x, goal_y), Some(tile) if tile.is_walkable());
+
+        if is_goal_adjacent && is_goal_walkable && !exits.iter().any(|(i, _)| *i == self.goal) {
+            let cost = if dx != 0 && dy != 0 { 1.45 } else { 1.0 };
+            exits.push((self.goal, cost));
+        }
+
+        exits
+    }
+}
+
+impl<'a> Algorithm2D for GoalTileMap<'a> {
+    fn dimensions(&self) -> Point {
+        self.map.dimensions()
+    }
+}
+
+/// Tint a color by the light falling on its tile
+fn apply_light(color: RGB, light: RGB) -> RGB {
+    RGB::from_f32(
+        color.r * light.r.max(MIN_LIGHT_TINT),
+        color.g * light.g.max(MIN_LIGHT_TINT),
+        color.b * light.b.max(MIN_LIGHT_TINT),
+    )
+}
+
 pub fn draw_map(context: &mut BTerm, world: &World, config: &Config) {
     for (_, map) in world.query::<&mut TileMap>().into_iter() {
         for (i, tile) in map.tiles.iter().enumerate() {
             if map.revealed_tiles[i] {
                 let (x, y) = map.get_coords(i);
                 let is_visible = map.visible_tiles[i];
-                context.set(
-                    x,
-                    y,
-                    tile.fg(config, is_visible),
-                    tile.bg(config, is_visible),
-                    tile.glyph(config),
-                );
+
+                let (fg, bg) = if is_visible {
+                    (
+                        apply_light(tile.fg(config, true), map.light[i]),
+                        apply_light(tile.bg(config, true), map.light[i]),
+                    )
+                } else {
+                    (tile.fg(config, false), tile.bg(config, false))
+                };
+
+                context.set(x, y, fg, bg, tile.glyph(config));
             }
         }
 
@@ -343,5 +664,70 @@ pub fn draw_map(context: &mut BTerm, world: &World, config: &Config) {
         {
             context.set(pos.x, pos.y, render.fg, render.bg, render.glyph);
         }
+
+        // Effects are drawn over the lit background of their tile, on top of everything else
+        let lit_bg = |x: i32, y: i32| match (map.get_index(x, y), map.get_tile(x, y)) {
+            (Some(i), Some(tile)) if map.visible_tiles[i] => {
+                Some(apply_light(tile.bg(config, true), map.light[i]))
+            }
+            _ => None,
+        };
+
+        for (_, animations) in world.query::<&ProjectileAnimations>().into_iter() {
+            for animation in animations.0.iter() {
+                if let Some(point) = animation_tile(animation) {
+                    if let Some(bg) = lit_bg(point.x, point.y) {
+                        context.set(point.x, point.y, animation.fg, bg, animation.glyph);
+                    }
+                }
+            }
+        }
+
+        for (_, particles) in world.query::<&Particles>().into_iter() {
+            for particle in particles
+                .0
+                .iter()
+                .filter(|particle| is_particle_shown(particle))
+            {
+                if let Some(bg) = lit_bg(particle.x, particle.y) {
+                    context.set(
+                        particle.x,
+                        particle.y,
+                        particle.fg,
+                        particle.bg.unwrap_or(bg),
+                        particle.glyph,
+                    );
+                }
+            }
+        }
+
+        for (i, remembered) in map.remembered_content.iter().enumerate() {
+            if map.visible_tiles[i] {
+                continue;
+            }
+
+            if let Some(render) = remembered
+                .iter()
+                .filter_map(|memory| memory.renderable.as_ref())
+                .max_by_key(|r| r.render_order)
+            {
+                let (x, y) = map.get_coords(i);
+                context.set(x, y, render.fog_fg, render.fog_bg, render.glyph);
+            }
+        }
+
+        for (_, (_, pos, render)) in world
+            .query::<(&Detected, &Position, &Renderable)>()
+            .into_iter()
+            .filter(|(_, (_, pos, _))| !map.is_tile_visible(pos.x, pos.y))
+        {
+            context.set(
+                pos.x,
+                pos.y,
+                config.ui_detected.fg,
+                render.fog_bg,
+                render.glyph,
+            );
+        }
     }
 }
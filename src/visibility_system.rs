@@ -3,10 +3,13 @@ use crate::prelude::*;
 pub fn visibility_system(world: &mut World) {
     let player_entity = world.resource_entity::<Player>().ok();
 
-    for (entity, (viewshed, pos)) in world.query::<(&mut Viewshed, &Position)>().into_iter() {
+    for (entity, (viewshed, pos, darkvision)) in world
+        .query::<(&mut Viewshed, &Position, Option<&Darkvision>)>()
+        .into_iter()
+    {
         if viewshed.dirty {
             if let Some((_, map)) = world.query::<&mut TileMap>().into_iter().next() {
-                update_viewshed(viewshed, pos, map);
+                update_viewshed(viewshed, pos, darkvision.is_some(), map);
 
                 // If this is the player, reveal what they can see
                 if Some(entity) == player_entity {
@@ -17,13 +20,23 @@ pub fn visibility_system(world: &mut World) {
     }
 }
 
-fn update_viewshed(viewshed: &mut Viewshed, pos: &Position, map: &TileMap) {
+fn update_viewshed(viewshed: &mut Viewshed, pos: &Position, has_darkvision: bool, map: &TileMap) {
     viewshed.dirty = false;
-    viewshed.visible_tiles.clear();
-    viewshed.visible_tiles = field_of_view(pos.to_point(), viewshed.range, &*map);
     viewshed
         .visible_tiles
-        .retain(|p| p.x >= 0 && p.x < map.get_width() && p.y >= 0 && p.y < map.get_height());
+        .reset(map.get_width(), map.get_height());
+
+    let origin = pos.to_point();
+
+    for p in field_of_view(origin, viewshed.range, &*map) {
+        // Without darkvision, only lit tiles and those close enough to touch can be seen
+        if has_darkvision
+            || map.is_tile_lit(p.x, p.y)
+            || DistanceAlg::Pythagoras.distance2d(origin, p) < 1.5
+        {
+            viewshed.visible_tiles.set(p.x, p.y, true);
+        }
+    }
 }
 
 fn apply_viewshed_to_map(viewshed: &mut Viewshed, map: &mut TileMap) {